@@ -0,0 +1,595 @@
+use crate::SyntaxError;
+
+/// the index of a token in the token list a tree was parsed from
+///
+/// trivia like comments and line breaks are not part of the tree, they are found between the
+/// tokens the tree refers to
+pub type TokenIdx = usize;
+
+/// a parsed source file
+#[derive(Debug)]
+pub struct TranslationUnit {
+    pub items: Vec<Item>,
+}
+
+/// a construct at file scope
+#[derive(Debug)]
+pub enum Item {
+    Directive(Directive),
+    Declaration(Declaration),
+    Function(FunctionDef),
+    StaticAssert(StaticAssert),
+    Macro(MacroCall),
+    /// a stray `;`
+    Empty(TokenIdx),
+    Verbatim(Verbatim),
+}
+
+/// the kind of a preprocessor directive, decided by the word after the `#`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveKind {
+    /// `#if`, `#ifdef` and `#ifndef`
+    If,
+    /// `#elif`, `#elifdef` and `#elifndef`
+    Elif,
+    Else,
+    Endif,
+    Include,
+    Define,
+    Other,
+}
+
+/// a preprocessor directive, including the line continuations and comments on its lines
+#[derive(Debug)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    /// the `#`
+    pub start: TokenIdx,
+    /// the line break ending the directive, or the number of tokens at the end of the input
+    pub end: TokenIdx,
+}
+
+/// the tokens `start..end`, which are passed through as they appear in the source
+#[derive(Debug)]
+pub struct Verbatim {
+    pub start: TokenIdx,
+    pub end: TokenIdx,
+    /// why the tokens could not be parsed
+    pub error: SyntaxError,
+}
+
+/// a balanced pair of parentheses whose content is kept as a list of tokens, as in
+/// `__attribute__((packed))`
+#[derive(Debug)]
+pub struct Group {
+    pub open: TokenIdx,
+    pub close: TokenIdx,
+}
+
+/// a use of a configured statement macro, like `DECLARE_BITMAP(x, 64);`
+#[derive(Debug)]
+pub struct MacroCall {
+    pub name: TokenIdx,
+    pub args: Option<Group>,
+    /// missing for macros configured to be used without one
+    pub semi: Option<TokenIdx>,
+}
+
+/// a `static_assert(expr, "message");` declaration
+#[derive(Debug)]
+pub struct StaticAssert {
+    pub keyword: TokenIdx,
+    pub args: Args,
+    pub semi: TokenIdx,
+}
+
+/// a declaration like `static int a = 1, *b;`
+#[derive(Debug)]
+pub struct Declaration {
+    pub specifiers: Vec<Specifier>,
+    pub declarators: Vec<InitDeclarator>,
+    /// the commas between the declarators
+    pub commas: Vec<TokenIdx>,
+    pub semi: TokenIdx,
+}
+
+/// a declaration specifier
+#[derive(Debug)]
+pub enum Specifier {
+    /// a keyword or a type name like `static`, `int` or `size_t`
+    Token(TokenIdx),
+    /// a keyword or macro with parenthesized arguments like `__attribute__((packed))`,
+    /// `_Alignas(8)` or `STACK_OF(X509)`
+    Call(TokenIdx, Group),
+    Attribute(Attribute),
+    Record(Record),
+    Enum(Enum),
+}
+
+/// a standard attribute like `[[nodiscard]]` or `[[deprecated("use g")]]`, whose content is kept
+/// as a list of tokens
+#[derive(Debug)]
+pub struct Attribute {
+    /// the first `[`
+    pub open: TokenIdx,
+    /// the last `]`
+    pub close: TokenIdx,
+}
+
+/// a `struct` or `union` specifier
+#[derive(Debug)]
+pub struct Record {
+    pub keyword: TokenIdx,
+    pub attributes: Vec<Specifier>,
+    pub name: Option<TokenIdx>,
+    pub body: Option<RecordBody>,
+}
+
+#[derive(Debug)]
+pub struct RecordBody {
+    pub open: TokenIdx,
+    pub members: Vec<Member>,
+    pub close: TokenIdx,
+}
+
+/// an entry of a struct or union body
+#[derive(Debug)]
+pub enum Member {
+    Field(Declaration),
+    StaticAssert(StaticAssert),
+    Macro(MacroCall),
+    Directive(Directive),
+}
+
+/// an `enum` specifier
+#[derive(Debug)]
+pub struct Enum {
+    pub keyword: TokenIdx,
+    pub attributes: Vec<Specifier>,
+    pub name: Option<TokenIdx>,
+    pub body: Option<EnumBody>,
+}
+
+#[derive(Debug)]
+pub struct EnumBody {
+    pub open: TokenIdx,
+    pub entries: Vec<EnumEntry>,
+    pub close: TokenIdx,
+}
+
+#[derive(Debug)]
+pub enum EnumEntry {
+    Enumerator(Enumerator),
+    Directive(Directive),
+}
+
+/// an enumerator like `A = 1,`
+#[derive(Debug)]
+pub struct Enumerator {
+    pub name: TokenIdx,
+    /// the `=` and the value
+    pub value: Option<(TokenIdx, Expr)>,
+    pub comma: Option<TokenIdx>,
+}
+
+/// a declarator with its bitfield width and initializer
+#[derive(Debug)]
+pub struct InitDeclarator {
+    pub declarator: Declarator,
+    /// attributes and asm labels following the declarator
+    pub attributes: Vec<Specifier>,
+    /// the `:` and the width
+    pub bitfield: Option<(TokenIdx, Expr)>,
+    /// the `=` and the initializer
+    pub init: Option<(TokenIdx, Expr)>,
+}
+
+/// the part of a declaration that names the declared entity, like `*const names[4]`
+#[derive(Debug)]
+pub struct Declarator {
+    pub pointers: Vec<Pointer>,
+    pub direct: DirectDeclarator,
+}
+
+/// a `*` of a declarator with its qualifiers
+#[derive(Debug)]
+pub struct Pointer {
+    pub star: TokenIdx,
+    pub qualifiers: Vec<Specifier>,
+}
+
+#[derive(Debug)]
+pub enum DirectDeclarator {
+    /// the declarator has no name, as in the type name `char *`
+    Abstract,
+    Name(TokenIdx),
+    Paren {
+        open: TokenIdx,
+        inner: Box<Declarator>,
+        close: TokenIdx,
+    },
+    Array {
+        inner: Box<DirectDeclarator>,
+        open: TokenIdx,
+        /// keywords like `static` and `const` before the size
+        qualifiers: Vec<TokenIdx>,
+        size: Option<Expr>,
+        close: TokenIdx,
+    },
+    Function {
+        inner: Box<DirectDeclarator>,
+        params: ParamList,
+    },
+}
+
+impl Declarator {
+    /// the declared name, if there is one
+    pub fn name(&self) -> Option<TokenIdx> {
+        self.direct.name()
+    }
+
+    /// the parameters if this declares a function
+    pub fn params(&self) -> Option<&ParamList> {
+        self.direct.params()
+    }
+
+    /// the first token of the declarator, unless it is empty
+    pub fn first_token(&self) -> Option<TokenIdx> {
+        match self.pointers.first() {
+            Some(pointer) => Some(pointer.star),
+            None => self.direct.first_token(),
+        }
+    }
+}
+
+impl DirectDeclarator {
+    pub fn name(&self) -> Option<TokenIdx> {
+        match self {
+            DirectDeclarator::Abstract => None,
+            DirectDeclarator::Name(name) => Some(*name),
+            DirectDeclarator::Paren { inner, .. } => inner.name(),
+            DirectDeclarator::Array { inner, .. } | DirectDeclarator::Function { inner, .. } => {
+                inner.name()
+            }
+        }
+    }
+
+    pub fn params(&self) -> Option<&ParamList> {
+        match self {
+            DirectDeclarator::Function { inner, params } => match **inner {
+                DirectDeclarator::Name(_) => Some(params),
+                _ => inner.params(),
+            },
+            DirectDeclarator::Paren { inner, .. } => inner.params(),
+            DirectDeclarator::Array { .. }
+            | DirectDeclarator::Abstract
+            | DirectDeclarator::Name(_) => None,
+        }
+    }
+
+    pub fn first_token(&self) -> Option<TokenIdx> {
+        match self {
+            DirectDeclarator::Abstract => None,
+            DirectDeclarator::Name(idx) | DirectDeclarator::Paren { open: idx, .. } => Some(*idx),
+            DirectDeclarator::Array { inner, .. } | DirectDeclarator::Function { inner, .. } => {
+                inner.first_token()
+            }
+        }
+    }
+}
+
+/// the parenthesized parameters of a function declarator
+#[derive(Debug)]
+pub struct ParamList {
+    pub open: TokenIdx,
+    pub params: Vec<Param>,
+    pub commas: Vec<TokenIdx>,
+    pub close: TokenIdx,
+}
+
+impl ParamList {
+    /// whether the parameters are only names, as in the old-style definition `int f(a, b)`
+    pub fn is_identifier_list(&self) -> bool {
+        !self.params.is_empty() && self.params.iter().all(|param| param.name_only().is_some())
+    }
+}
+
+#[derive(Debug)]
+pub enum Param {
+    Declaration {
+        specifiers: Vec<Specifier>,
+        declarator: Declarator,
+        attributes: Vec<Specifier>,
+    },
+    /// the `...` of a variadic function
+    Ellipsis(TokenIdx),
+}
+
+impl Param {
+    /// the name if the parameter is a name without a type
+    pub fn name_only(&self) -> Option<TokenIdx> {
+        match self {
+            Param::Declaration {
+                specifiers,
+                declarator:
+                    Declarator {
+                        pointers,
+                        direct: DirectDeclarator::Name(name),
+                    },
+                attributes,
+            } if specifiers.is_empty() && pointers.is_empty() && attributes.is_empty() => {
+                Some(*name)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// a type as used in casts and `sizeof`
+#[derive(Debug)]
+pub struct TypeName {
+    pub specifiers: Vec<Specifier>,
+    pub declarator: Declarator,
+}
+
+/// a function definition
+#[derive(Debug)]
+pub struct FunctionDef {
+    pub specifiers: Vec<Specifier>,
+    pub declarator: Declarator,
+    pub attributes: Vec<Specifier>,
+    /// the parameter declarations of an old-style definition like
+    /// `int f(a, b) int a; char *b; { ... }`
+    pub param_declarations: Vec<Declaration>,
+    pub body: Block,
+}
+
+/// a compound statement
+#[derive(Debug)]
+pub struct Block {
+    pub open: TokenIdx,
+    pub stmts: Vec<Stmt>,
+    pub close: TokenIdx,
+}
+
+/// a parenthesized condition as in `if` and `while`
+#[derive(Debug)]
+pub struct Condition {
+    pub open: TokenIdx,
+    pub expr: Expr,
+    pub close: TokenIdx,
+}
+
+/// the first clause of a `for` statement
+#[derive(Debug)]
+pub enum ForInit {
+    Declaration(Declaration),
+    Expr(Option<Expr>, TokenIdx),
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Directive(Directive),
+    Verbatim(Verbatim),
+    Declaration(Declaration),
+    StaticAssert(StaticAssert),
+    Expr {
+        expr: Expr,
+        semi: TokenIdx,
+    },
+    /// a `;` on its own
+    Empty(TokenIdx),
+    Block(Block),
+    If {
+        keyword: TokenIdx,
+        cond: Condition,
+        then: Box<Stmt>,
+        /// the `else` and its statement
+        otherwise: Option<(TokenIdx, Box<Stmt>)>,
+    },
+    Switch {
+        keyword: TokenIdx,
+        cond: Condition,
+        body: Box<Stmt>,
+    },
+    While {
+        keyword: TokenIdx,
+        cond: Condition,
+        body: Box<Stmt>,
+    },
+    DoWhile {
+        do_keyword: TokenIdx,
+        body: Box<Stmt>,
+        while_keyword: TokenIdx,
+        cond: Condition,
+        semi: TokenIdx,
+    },
+    For {
+        keyword: TokenIdx,
+        open: TokenIdx,
+        init: ForInit,
+        cond: Option<Expr>,
+        cond_semi: TokenIdx,
+        step: Option<Expr>,
+        close: TokenIdx,
+        body: Box<Stmt>,
+    },
+    /// a loop over a configured for-each macro, like `LIST_FOREACH(it, list) { ... }`
+    ForEach {
+        name: TokenIdx,
+        args: Group,
+        body: Box<Stmt>,
+    },
+    Macro(MacroCall),
+    /// a `case` label, the statements following it are separate entries of the block
+    Case {
+        keyword: TokenIdx,
+        value: Expr,
+        colon: TokenIdx,
+    },
+    Default {
+        keyword: TokenIdx,
+        colon: TokenIdx,
+    },
+    /// a label for `goto`
+    Label {
+        name: TokenIdx,
+        colon: TokenIdx,
+    },
+    /// `return`, `break`, `continue` and `goto`
+    Jump {
+        keyword: TokenIdx,
+        value: Option<Expr>,
+        semi: TokenIdx,
+    },
+}
+
+/// the parenthesized arguments of a call
+#[derive(Debug)]
+pub struct Args {
+    pub open: TokenIdx,
+    pub args: Vec<Expr>,
+    pub commas: Vec<TokenIdx>,
+    pub close: TokenIdx,
+}
+
+/// a brace enclosed initializer list
+#[derive(Debug)]
+pub struct InitList {
+    pub open: TokenIdx,
+    pub entries: Vec<InitEntry>,
+    pub close: TokenIdx,
+}
+
+#[derive(Debug)]
+pub enum InitEntry {
+    Value {
+        designators: Vec<Designator>,
+        /// the `=` following the designators
+        eq: Option<TokenIdx>,
+        value: Expr,
+        comma: Option<TokenIdx>,
+    },
+    Directive(Directive),
+}
+
+/// an association of a generic selection like `, int: f`
+#[derive(Debug)]
+pub struct Association {
+    /// the comma preceding the association
+    pub comma: TokenIdx,
+    pub selector: Selector,
+    pub colon: TokenIdx,
+    pub value: Expr,
+}
+
+/// the type an association of a generic selection applies to
+#[derive(Debug)]
+pub enum Selector {
+    Type(TypeName),
+    Default(TokenIdx),
+}
+
+/// a designator like `.name` or `[2]`
+#[derive(Debug)]
+pub enum Designator {
+    Field {
+        dot: TokenIdx,
+        name: TokenIdx,
+    },
+    Index {
+        open: TokenIdx,
+        index: Expr,
+        close: TokenIdx,
+    },
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Ident(TokenIdx),
+    Const(TokenIdx),
+    /// adjacent string literals, possibly with macros like `PRIu64` between them
+    Str(Vec<TokenIdx>),
+    Paren {
+        open: TokenIdx,
+        inner: Box<Expr>,
+        close: TokenIdx,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Args,
+    },
+    Index {
+        base: Box<Expr>,
+        open: TokenIdx,
+        index: Box<Expr>,
+        close: TokenIdx,
+    },
+    /// a member access with `.` or `->`
+    Member {
+        base: Box<Expr>,
+        op: TokenIdx,
+        name: TokenIdx,
+    },
+    /// `x++` and `x--`
+    Postfix {
+        operand: Box<Expr>,
+        op: TokenIdx,
+    },
+    /// a prefix operator, including `sizeof` applied to an expression
+    Unary {
+        op: TokenIdx,
+        operand: Box<Expr>,
+    },
+    /// `sizeof` or `_Alignof` applied to a type
+    SizeofType {
+        keyword: TokenIdx,
+        open: TokenIdx,
+        type_name: Box<TypeName>,
+        close: TokenIdx,
+    },
+    Cast {
+        open: TokenIdx,
+        type_name: Box<TypeName>,
+        close: TokenIdx,
+        operand: Box<Expr>,
+    },
+    /// a compound literal like `(struct point){ .x = 1 }`
+    CompoundLiteral {
+        open: TokenIdx,
+        type_name: Box<TypeName>,
+        close: TokenIdx,
+        init: InitList,
+    },
+    /// a generic selection like `_Generic(x, int: f, default: g)`
+    Generic {
+        keyword: TokenIdx,
+        open: TokenIdx,
+        controlling: Box<Expr>,
+        associations: Vec<Association>,
+        close: TokenIdx,
+    },
+    /// a binary operator, including assignments and the comma operator
+    Binary {
+        lhs: Box<Expr>,
+        op: TokenIdx,
+        rhs: Box<Expr>,
+    },
+    Ternary {
+        cond: Box<Expr>,
+        question: TokenIdx,
+        /// missing for the GNU extension `a ?: b`
+        then: Option<Box<Expr>>,
+        colon: TokenIdx,
+        otherwise: Box<Expr>,
+    },
+    InitList(InitList),
+    /// a GNU statement expression `({ ... })`
+    StmtExpr {
+        open: TokenIdx,
+        block: Block,
+        close: TokenIdx,
+    },
+    /// a type passed to a macro, as in `va_arg(ap, int)`
+    Type(Box<TypeName>),
+}
@@ -0,0 +1,563 @@
+/// options controlling how code is formatted
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// the number of columns lines are kept within where possible
+    pub column_limit: usize,
+    pub indentation: Indentation,
+    pub labels: Labels,
+    pub blank_lines: BlankLines,
+    pub directives: Directives,
+    pub includes: Includes,
+    pub braces: Braces,
+    pub spacing: Spacing,
+    pub pointer_alignment: PointerAlignment,
+    /// how the arguments of calls and macro invocations are wrapped
+    pub arguments: Wrapping,
+    /// how the parameters of function declarations and definitions are wrapped
+    pub parameters: Wrapping,
+    pub enums: Enums,
+    pub alignment: Alignment,
+    pub comments: Comments,
+    pub penalties: Penalties,
+    pub macros: Macros,
+    /// rewrite old-style function definitions like `int f(a) int a; { ... }` to prototype style
+    /// when every parameter is declared exactly once
+    pub convert_old_style_definitions: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            column_limit: 80,
+            indentation: Indentation::default(),
+            labels: Labels::default(),
+            blank_lines: BlankLines::default(),
+            directives: Directives::default(),
+            includes: Includes::default(),
+            braces: Braces::default(),
+            spacing: Spacing::default(),
+            pointer_alignment: PointerAlignment::Right,
+            arguments: Wrapping::default(),
+            parameters: Wrapping::default(),
+            enums: Enums::default(),
+            alignment: Alignment::default(),
+            comments: Comments::default(),
+            penalties: Penalties::default(),
+            macros: Macros::default(),
+            convert_old_style_definitions: false,
+        }
+    }
+}
+
+/// how lines are indented
+#[derive(Debug, Clone)]
+pub struct Indentation {
+    /// the number of columns the content of a block is indented by
+    pub width: usize,
+    /// the number of columns a line continuing a statement or declaration is indented by
+    pub continuation: usize,
+    /// the number of columns a tab stands for
+    pub tab_width: usize,
+    pub tabs: Tabs,
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Self {
+            width: 4,
+            continuation: 4,
+            tab_width: 8,
+            tabs: Tabs::Never,
+        }
+    }
+}
+
+/// where tabs are used in the whitespace at the start of a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tabs {
+    /// only spaces
+    Never,
+    /// tabs for the indentation of blocks and spaces for continuation lines and alignment, so
+    /// that aligned code stays aligned at any tab width
+    Smart,
+    /// as many tabs as fit
+    Always,
+}
+
+impl Indentation {
+    /// the whitespace before the first token on a line starting at column `col`, of which
+    /// `level` columns are block indentation
+    pub(crate) fn whitespace(&self, col: usize, level: usize) -> String {
+        let tab_width = self.tab_width.max(1);
+        let tabs = match self.tabs {
+            Tabs::Never => 0,
+            Tabs::Smart => level.min(col) / tab_width,
+            Tabs::Always => col / tab_width,
+        };
+        "\t".repeat(tabs) + &" ".repeat(col - tabs * tab_width)
+    }
+}
+
+/// how `case` and `goto` labels and the statements under them are indented
+#[derive(Debug, Clone)]
+pub struct Labels {
+    /// indent `case` and `default` labels inside the braces of a `switch`, rather than putting
+    /// them at the level of the braces
+    pub indent_cases: bool,
+    /// indent the statements under a `case` label further than the label
+    pub indent_case_bodies: bool,
+    pub case_blocks: CaseBlocks,
+    pub goto_labels: GotoLabels,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Self {
+            indent_cases: false,
+            indent_case_bodies: true,
+            case_blocks: CaseBlocks::Indented,
+            goto_labels: GotoLabels::Outdented,
+        }
+    }
+}
+
+/// where a block following a `case` label goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseBlocks {
+    /// on the line after the label, indented like the statements under it
+    Indented,
+    /// on the line after the label, at the level of the label
+    NextLine,
+    /// `case X: {`, with the closing brace at the level of the label
+    Attached,
+}
+
+/// where labels for `goto` go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GotoLabels {
+    /// at the start of the line
+    FlushLeft,
+    /// a level of indentation to the left of the statements around them
+    Outdented,
+    /// at the level of the statements around them
+    WithCode,
+}
+
+/// where empty lines are kept, added and removed
+#[derive(Debug, Clone)]
+pub struct BlankLines {
+    /// the most empty lines in a row that are kept
+    pub max: usize,
+    /// the least number of empty lines between two function definitions
+    pub between_functions: usize,
+    /// remove the empty lines after the opening brace and before the closing brace of a body
+    pub trim_bodies: bool,
+    /// put an empty line after the declarations before a statement in a block
+    pub after_declarations: bool,
+}
+
+impl Default for BlankLines {
+    fn default() -> Self {
+        Self {
+            max: 1,
+            between_functions: 0,
+            trim_bodies: true,
+            after_declarations: false,
+        }
+    }
+}
+
+/// how preprocessor directives are indented by the depth of the conditionals around them
+#[derive(Debug, Clone)]
+pub struct Directives {
+    pub indent: DirectiveIndent,
+    /// the number of columns for each level of nesting
+    pub width: usize,
+    /// do not count the conditional of an include guard around the whole file
+    pub skip_include_guard: bool,
+}
+
+impl Default for Directives {
+    fn default() -> Self {
+        Self {
+            indent: DirectiveIndent::None,
+            width: 2,
+            skip_include_guard: true,
+        }
+    }
+}
+
+/// where the indentation of a nested directive goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveIndent {
+    /// directives start at the first column and keep their spacing
+    None,
+    /// `#  define`
+    AfterHash,
+    /// `  #define`
+    BeforeHash,
+}
+
+/// how the lines of blocks of `#include` directives are sorted
+///
+/// a block ends at an empty line or any other line, like a conditional directive. the includes
+/// of a block are put in the order of their categories, and sorted by name within each category
+#[derive(Debug, Clone)]
+pub struct Includes {
+    pub sort: bool,
+    /// the header declaring what the file being formatted defines, like `list.h` for `list.c`,
+    /// whose include goes first
+    pub main_header: Option<String>,
+    /// the regular expressions for the categories, in the order they go in. a header is matched
+    /// with its quotes or angle brackets, like `<stdio.h>`, and belongs to the first category
+    /// it matches. headers matching none, or only patterns that are not valid, go last
+    pub categories: Vec<String>,
+    /// put an empty line between the categories of a block
+    pub separate_categories: bool,
+}
+
+impl Default for Includes {
+    fn default() -> Self {
+        Self {
+            sort: true,
+            main_header: None,
+            categories: vec![
+                // the headers of the system and of the standard library
+                r"^<(sys/)?[a-z0-9_]+\.h>$".to_string(),
+                // other libraries
+                "^<".to_string(),
+                // the headers of the project
+                "^\"".to_string(),
+            ],
+            separate_categories: false,
+        }
+    }
+}
+
+/// where the opening brace of a body goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BracePlacement {
+    /// at the end of the line before, as in `if (x) {`
+    Attached,
+    /// on a line of its own, at the indentation of the code before it
+    NextLine,
+    /// on a line of its own, indented by half a level, with the body indented by another half
+    Gnu,
+    /// on a line of its own, indented by a level along with the body
+    Whitesmiths,
+}
+
+/// where braces go around the bodies of functions, statements and types
+#[derive(Debug, Clone)]
+pub struct Braces {
+    pub functions: BracePlacement,
+    /// the bodies of `if`, `else`, `for`, `while`, `do` and `switch`
+    pub statements: BracePlacement,
+    /// the bodies of `struct`, `union` and `enum`
+    pub types: BracePlacement,
+    /// put `else` on the line of the closing brace before it, as in `} else {`
+    pub cuddle_else: bool,
+    /// put the `while` of a `do` statement on the line of the closing brace before it
+    pub cuddle_while: bool,
+}
+
+impl Braces {
+    /// braces on their own line for functions only, `} else` and `} while` cuddled
+    pub fn k_and_r() -> Self {
+        Self {
+            functions: BracePlacement::NextLine,
+            statements: BracePlacement::Attached,
+            types: BracePlacement::Attached,
+            cuddle_else: true,
+            cuddle_while: true,
+        }
+    }
+
+    /// the style of the linux kernel, which places braces like [`Braces::k_and_r`]
+    pub fn linux() -> Self {
+        Self::k_and_r()
+    }
+
+    /// like [`Braces::k_and_r`], with `else` and `while` on a line of their own
+    pub fn stroustrup() -> Self {
+        Self {
+            cuddle_else: false,
+            cuddle_while: false,
+            ..Self::k_and_r()
+        }
+    }
+
+    /// every brace on a line of its own
+    pub fn allman() -> Self {
+        Self {
+            functions: BracePlacement::NextLine,
+            statements: BracePlacement::NextLine,
+            types: BracePlacement::NextLine,
+            cuddle_else: false,
+            cuddle_while: false,
+        }
+    }
+
+    /// every brace on a line of its own, the braces of statements indented by half a level
+    pub fn gnu() -> Self {
+        Self {
+            statements: BracePlacement::Gnu,
+            ..Self::allman()
+        }
+    }
+
+    /// every brace on a line of its own, indented along with the body
+    pub fn whitesmiths() -> Self {
+        Self {
+            functions: BracePlacement::Whitesmiths,
+            statements: BracePlacement::Whitesmiths,
+            types: BracePlacement::Whitesmiths,
+            cuddle_else: false,
+            cuddle_while: false,
+        }
+    }
+}
+
+impl Default for Braces {
+    fn default() -> Self {
+        Self::k_and_r()
+    }
+}
+
+/// where spaces go before parentheses
+#[derive(Debug, Clone)]
+pub struct Spacing {
+    /// a space between a function and its arguments or parameters, as in `f (x)`
+    pub before_call_parens: bool,
+    /// a space between `if`, `for`, `while` or `switch` and its parenthesis, as in `if (x)`
+    pub before_control_parens: bool,
+    pub bitfield_colons: BitfieldSpacing,
+}
+
+impl Default for Spacing {
+    fn default() -> Self {
+        Self {
+            before_call_parens: false,
+            before_control_parens: true,
+            bitfield_colons: BitfieldSpacing::Both,
+        }
+    }
+}
+
+/// the spaces around the `:` of a bitfield
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitfieldSpacing {
+    /// `int a : 3`
+    Both,
+    /// `int a :3`
+    Before,
+    /// `int a: 3`
+    After,
+    /// `int a:3`
+    None,
+}
+
+/// where the `*` of a pointer goes between the type and the name
+///
+/// declarations of several names are always written like `int *a, *b`, since `int* a, b` reads
+/// as if `b` was a pointer too
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerAlignment {
+    /// `int* p`
+    Left,
+    /// `int * p`
+    Middle,
+    /// `int *p`
+    Right,
+}
+
+/// how a parenthesized list that does not fit on a line is wrapped
+#[derive(Debug, Clone, Copy)]
+pub struct Wrapping {
+    /// put as many entries on each line as fit, rather than one entry per line
+    pub bin_pack: bool,
+    /// line up the entries after the opening parenthesis, rather than breaking after it and
+    /// indenting the entries as a continuation, with the closing parenthesis on a line of its own
+    pub align_after_paren: bool,
+}
+
+impl Default for Wrapping {
+    fn default() -> Self {
+        Self {
+            bin_pack: false,
+            align_after_paren: true,
+        }
+    }
+}
+
+/// how the bodies of enums are laid out
+///
+/// the values of enumerators are lined up with [`Alignment::assignments`] and the comments after
+/// them with [`Alignment::trailing_comments`]
+#[derive(Debug, Clone)]
+pub struct Enums {
+    /// put an enum on one line if it fits, like `enum dir { UP, DOWN };`, rather than one
+    /// enumerator per line. an enum ending with a comma is always written one enumerator per line
+    pub one_line: bool,
+    pub trailing_comma: TrailingComma,
+}
+
+impl Default for Enums {
+    fn default() -> Self {
+        Self {
+            one_line: true,
+            trailing_comma: TrailingComma::Keep,
+        }
+    }
+}
+
+/// what happens to the comma after the last entry of a list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailingComma {
+    /// it is left as it is in the source
+    Keep,
+    Add,
+    Remove,
+}
+
+/// what is lined up across consecutive lines
+#[derive(Debug, Clone)]
+pub struct Alignment {
+    /// the assignment operators of declarations and assignments
+    pub assignments: bool,
+    /// the names declared by declarations and parameters
+    pub declarations: bool,
+    /// the `:` of bitfields, along with their widths
+    pub bitfields: bool,
+    /// the elements of braced initializers on consecutive lines, like the rows of a table
+    pub tables: bool,
+    /// the comments at the end of lines
+    pub trailing_comments: bool,
+    /// the largest number of columns a trailing comment is moved by, a comment that would have
+    /// to move further starts a new run of aligned comments
+    pub max_comment_shift: usize,
+    /// the `\` continuing the lines of a directive
+    pub backslashes: Backslashes,
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self {
+            assignments: true,
+            declarations: true,
+            bitfields: true,
+            tables: true,
+            trailing_comments: true,
+            max_comment_shift: 16,
+            backslashes: Backslashes::Aligned,
+        }
+    }
+}
+
+/// how comments on lines of their own are rewritten
+#[derive(Debug, Clone)]
+pub struct Comments {
+    /// wrap comments with lines past the column limit, keeping paragraphs, lists, indented code
+    /// and diagrams. comments at the end of a line of code are left as they are
+    pub reflow: bool,
+    /// put the `/**` and `*/` of doc comments on lines of their own with ` * ` before each line
+    /// between them, and line up the descriptions of commands like `@param`. also warns about
+    /// `@param` names that are not parameters of the function
+    pub doxygen: bool,
+}
+
+impl Default for Comments {
+    fn default() -> Self {
+        Self {
+            reflow: true,
+            doxygen: true,
+        }
+    }
+}
+
+/// where the `\` at the end of the lines of a multi-line directive goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backslashes {
+    /// a single space after the text of each line
+    Space,
+    /// one column for the whole directive, a space after its longest line
+    Aligned,
+    /// the last column within the column limit, or after the longest line if it does not fit
+    ColumnLimit,
+}
+
+/// the costs of the ways to lay out a line that does not fit, the cheapest layout is taken
+#[derive(Debug, Clone)]
+pub struct Penalties {
+    /// for each column past the column limit
+    pub excess_character: usize,
+    /// for a line break after an assignment or the `=` of an initializer
+    pub assignment: usize,
+    /// for breaking the line between each of the arguments of a call or the parameters of a
+    /// function
+    pub arguments: usize,
+    /// for breaking the line after each of a run of binary operators of the same precedence,
+    /// multiplied by how tightly they bind, so that `a && b || c && d` is rather broken after
+    /// the `||`
+    pub binary_operator: usize,
+    /// for breaking the lines of a conditional expression `a ? b : c`
+    pub conditional: usize,
+    /// for breaking the line between each of the declarators, initializers, the clauses of a
+    /// `for` loop or adjacent strings
+    pub list: usize,
+}
+
+impl Default for Penalties {
+    fn default() -> Self {
+        Self {
+            excess_character: 100,
+            assignment: 10,
+            arguments: 20,
+            binary_operator: 5,
+            conditional: 15,
+            list: 10,
+        }
+    }
+}
+
+/// names of macros that mimic language constructs, which cannot be told from their uses
+#[derive(Debug, Clone, Default)]
+pub struct Macros {
+    /// macros used like the head of a `for` loop, as in `LIST_FOREACH(it, list) { ... }`
+    pub for_each: Vec<String>,
+    /// macros used as a statement or declaration of their own, as in `DECLARE_BITMAP(x, 64);`
+    pub statement: Vec<String>,
+    /// macros expanding to a type, as in `STACK_OF(X509) *certs;`
+    pub types: Vec<String>,
+    /// macros expanding to attributes, as in `static __always_inline int f(void);`
+    pub attributes: Vec<String>,
+    /// macros used as a statement or declaration that are not followed by a `;`
+    pub no_semicolon: Vec<String>,
+}
+
+fn contains(names: &[String], name: &str) -> bool {
+    names.iter().any(|macro_name| macro_name == name)
+}
+
+impl Macros {
+    pub fn is_for_each(&self, name: &str) -> bool {
+        contains(&self.for_each, name)
+    }
+
+    /// whether `name` is used as a statement, with or without a `;` following it
+    pub fn is_statement(&self, name: &str) -> bool {
+        contains(&self.statement, name) || self.is_no_semicolon(name)
+    }
+
+    pub fn is_type(&self, name: &str) -> bool {
+        contains(&self.types, name)
+    }
+
+    pub fn is_attribute(&self, name: &str) -> bool {
+        contains(&self.attributes, name)
+    }
+
+    pub fn is_no_semicolon(&self, name: &str) -> bool {
+        contains(&self.no_semicolon, name)
+    }
+}
@@ -0,0 +1,618 @@
+use std::cell::Cell;
+
+use crate::{Config, Location, Token, TokenType};
+
+/// a description of the layout of a program, which is rendered to fit a column limit
+///
+/// horizontal space only appears where it is asked for with [`Doc::Spaces`] or [`Doc::Line`],
+/// line breaks never leave trailing whitespace and are never doubled up: a line break at the
+/// start of a line does nothing, empty lines are only made by [`Doc::BlankLine`].
+#[derive(Debug, Clone)]
+pub(crate) enum Doc<'a> {
+    Nil,
+    Token(Token<'a>),
+    /// whitespace between tokens on a line. dropped at the start of a line, and spaces next to
+    /// each other do not add up
+    Spaces(usize),
+    /// a line break if the enclosing group is broken, a space otherwise
+    Line,
+    /// a line break if the enclosing group is broken, nothing otherwise
+    SoftLine,
+    /// a line break, which also breaks all enclosing groups
+    HardLine,
+    /// a line break followed by an empty line
+    BlankLine,
+    /// indents the lines started inside by some columns, as a level of block indentation
+    Indent(isize, Box<Doc<'a>>),
+    /// indents the lines started inside by some columns past the start of the line on which it
+    /// starts, as continuations of that line. hangs on the same line do not add up
+    Hang(usize, Box<Doc<'a>>),
+    /// indents the lines started inside to the column at which it starts
+    Align(Box<Doc<'a>>),
+    /// indents the lines started inside to a fixed column
+    Column(usize, Box<Doc<'a>>),
+    /// puts the content on one line, or breaks all of its lines at the given cost. the groups
+    /// inside are laid out on their own
+    Group(usize, Box<Doc<'a>>),
+    Concat(Vec<Doc<'a>>),
+}
+
+impl<'a> Doc<'a> {
+    pub(crate) fn space() -> Self {
+        Doc::Spaces(1)
+    }
+
+    pub(crate) fn indent(columns: usize, doc: Doc<'a>) -> Self {
+        Doc::Indent(columns as isize, Box::new(doc))
+    }
+
+    /// like [`Doc::indent`], by a number of columns that may be negative
+    pub(crate) fn shift(columns: isize, doc: Doc<'a>) -> Self {
+        Doc::Indent(columns, Box::new(doc))
+    }
+
+    pub(crate) fn hang(columns: usize, doc: Doc<'a>) -> Self {
+        Doc::Hang(columns, Box::new(doc))
+    }
+
+    pub(crate) fn align(doc: Doc<'a>) -> Self {
+        Doc::Align(Box::new(doc))
+    }
+
+    pub(crate) fn column(column: usize, doc: Doc<'a>) -> Self {
+        Doc::Column(column, Box::new(doc))
+    }
+
+    pub(crate) fn group(penalty: usize, doc: Doc<'a>) -> Self {
+        Doc::Group(penalty, Box::new(doc))
+    }
+
+    /// joins `docs` with `separator` in between
+    pub(crate) fn join(docs: impl IntoIterator<Item = Doc<'a>>, separator: Doc<'a>) -> Self {
+        let mut joined = vec![];
+        for doc in docs {
+            if !joined.is_empty() {
+                joined.push(separator.clone());
+            }
+            joined.push(doc);
+        }
+        Doc::Concat(joined)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// a doc to lay out, along with the indentation of its lines and whether its lines are broken
+#[derive(Clone, Copy)]
+struct Command<'d, 'a> {
+    indent: isize,
+    /// the part of `indent` that is block indentation rather than alignment
+    level: isize,
+    mode: Mode,
+    doc: &'d Doc<'a>,
+}
+
+impl<'d, 'a> Command<'d, 'a> {
+    fn with(self, doc: &'d Doc<'a>) -> Self {
+        Self { doc, ..self }
+    }
+}
+
+/// the number of commands laid out for each layout of a group that does not fit when comparing
+/// them, shared with the layouts of the groups after it that are tried along the way
+const SEARCH_BUDGET: usize = 2000;
+
+/// the position of the output
+#[derive(Debug, Clone, Copy)]
+struct Cursor {
+    line: usize,
+    col: usize,
+    /// spaces to put before the next token on the line
+    pending: usize,
+    /// the number of line breaks since the last token
+    newlines: usize,
+    /// the column at which the current line starts, set by the last line break
+    line_indent: usize,
+    /// the part of `line_indent` that is block indentation
+    line_level: usize,
+}
+
+impl Cursor {
+    /// the column the next token is put at
+    fn next_col(&self) -> usize {
+        match self.newlines {
+            0 => self.col + self.pending,
+            _ => self.line_indent,
+        }
+    }
+
+    fn space(&mut self, n: usize) {
+        if self.newlines == 0 {
+            self.pending = self.pending.max(n);
+        }
+    }
+
+    /// moves past a token, returning the column it starts at
+    fn token(&mut self, token_type: TokenType) -> usize {
+        let col = self.next_col();
+        let text = token_type.to_str();
+        match text.rsplit_once(crate::LINE_ENDING) {
+            Some((head, tail)) => {
+                self.line += head.matches(crate::LINE_ENDING).count() + 1;
+                self.col = tail.chars().count();
+            }
+            None => self.col = col + token_type.width(),
+        }
+        self.pending = 0;
+        self.newlines = 0;
+        col
+    }
+
+    fn line_break(&mut self) {
+        self.line += 1;
+        self.col = 0;
+        self.pending = 0;
+        self.newlines += 1;
+    }
+
+    /// sets the indentation of the next line to that of `command`
+    fn indent(&mut self, command: &Command) {
+        let indent = command.indent.max(0) as usize;
+        self.line_indent = indent;
+        self.line_level = (command.level.max(0) as usize).min(indent);
+    }
+}
+
+/// the width of the first line of a token
+fn first_line_width(token_type: TokenType) -> usize {
+    let text = token_type.to_str();
+    let first_line = text.split(crate::LINE_ENDING).next().unwrap_or_default();
+    first_line.chars().count()
+}
+
+struct Renderer<'a> {
+    width: usize,
+    /// the cost of each column past the width
+    excess_penalty: usize,
+    out: Vec<Token<'a>>,
+    /// the columns of block indentation of each line
+    levels: Vec<usize>,
+    cursor: Cursor,
+    /// the number of commands laid out while comparing layouts
+    searched: Cell<usize>,
+}
+
+/// the tokens of a rendered doc
+#[derive(Debug)]
+pub(crate) struct Rendered<'a> {
+    pub(crate) tokens: Vec<Token<'a>>,
+    /// the number of columns at the start of each line that are block indentation, the rest of
+    /// the whitespace before the first token is alignment
+    pub(crate) levels: Vec<usize>,
+}
+
+/// lays out `doc` in lines of at most `config.column_limit` columns where possible, giving each
+/// token its location and adding a line break token at the end of each line
+///
+/// a group that fits on the rest of its line is not broken. otherwise the layouts of the group
+/// and of the groups following it up to the end of the logical line are compared by the
+/// penalties of the line breaks they make and of the columns past the limit, and the cheapest
+/// one is taken. the comparison looks at most [`SEARCH_BUDGET`] commands ahead, so that a long
+/// logical line like a large initializer is laid out in linear time.
+pub(crate) fn render<'a>(doc: &Doc<'a>, config: &Config) -> Rendered<'a> {
+    let mut renderer = Renderer::new(config);
+    renderer.run(doc);
+    if !renderer.out.is_empty() && renderer.cursor.newlines == 0 {
+        renderer.line_break();
+    }
+    Rendered {
+        tokens: renderer.out,
+        levels: renderer.levels,
+    }
+}
+
+impl<'a> Renderer<'a> {
+    fn new(config: &Config) -> Self {
+        Renderer {
+            width: config.column_limit,
+            excess_penalty: config.penalties.excess_character,
+            out: vec![],
+            levels: vec![],
+            cursor: Cursor {
+                line: 0,
+                col: 0,
+                pending: 0,
+                newlines: 1,
+                line_indent: 0,
+                line_level: 0,
+            },
+            searched: Cell::new(0),
+        }
+    }
+
+    fn run(&mut self, doc: &Doc<'a>) {
+        let mut stack = vec![Command {
+            indent: 0,
+            level: 0,
+            mode: Mode::Break,
+            doc,
+        }];
+        while let Some(command) = stack.pop() {
+            match command.doc {
+                Doc::Nil => {}
+                Doc::Token(token) => {
+                    let line = self.cursor.line;
+                    if self.cursor.newlines > 0 {
+                        self.levels.resize(line + 1, 0);
+                        self.levels[line] = self.cursor.line_level;
+                    }
+                    let col = self.cursor.token(token.token_type);
+                    self.out.push(Token::with_offset(
+                        token.token_type,
+                        Location::new(line, col),
+                        token.offset,
+                    ));
+                }
+                Doc::Spaces(n) => self.cursor.space(*n),
+                Doc::Line if command.mode == Mode::Flat => self.cursor.space(1),
+                Doc::SoftLine if command.mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(&command),
+                Doc::BlankLine => {
+                    while !self.out.is_empty() && self.cursor.newlines < 2 {
+                        self.line_break();
+                    }
+                    self.cursor.indent(&command);
+                }
+                Doc::Group(penalty, doc) => {
+                    let flat = Command {
+                        mode: Mode::Flat,
+                        ..command.with(doc)
+                    };
+                    let fits = self.fits(self.cursor, flat, &[], &stack);
+                    let broken = Command {
+                        mode: Mode::Break,
+                        ..flat
+                    };
+                    let cheaper_flat = !fits && {
+                        let flat_cost = self.cost(self.cursor, vec![flat], &stack, SEARCH_BUDGET);
+                        let broken_cost =
+                            self.cost(self.cursor, vec![broken], &stack, SEARCH_BUDGET);
+                        flat_cost < broken_cost.saturating_add(*penalty)
+                    };
+                    stack.push(if fits || cheaper_flat { flat } else { broken });
+                }
+                _ => self.push_nested(command, &mut stack),
+            }
+        }
+    }
+
+    /// pushes the content of a doc made of other docs
+    fn push_nested<'d>(&self, command: Command<'d, 'a>, stack: &mut Vec<Command<'d, 'a>>) {
+        push_nested(self.cursor, command, stack)
+    }
+
+    /// ends the line unless it is empty, the next line starts at the indentation of `command`
+    fn newline(&mut self, command: &Command) {
+        if self.cursor.newlines == 0 {
+            self.line_break();
+        }
+        self.cursor.indent(command);
+    }
+
+    fn line_break(&mut self) {
+        self.out.push(Token::new(
+            TokenType::Linebreak,
+            Location::new(self.cursor.line, self.cursor.col),
+        ));
+        self.cursor.line_break();
+    }
+
+    /// whether `command` fits on the rest of the line in flat mode, along with whatever follows
+    /// it up to the next line break. `local` and then `rest` are the commands following it, from
+    /// the last one to the next one
+    fn fits(
+        &self,
+        cursor: Cursor,
+        command: Command<'_, 'a>,
+        local: &[Command<'_, 'a>],
+        rest: &[Command<'_, 'a>],
+    ) -> bool {
+        let mut at_line_start = cursor.newlines > 0;
+        let mut pending = if at_line_start { 0 } else { cursor.pending };
+        let mut remaining = self.width as isize - (cursor.next_col() - pending) as isize;
+        let mut rest = local.iter().rev().chain(rest.iter().rev());
+        let mut stack = vec![(command.mode, command.doc)];
+
+        loop {
+            let (mode, doc) = match stack.pop() {
+                Some(command) => command,
+                None => match rest.next() {
+                    Some(command) => (command.mode, command.doc),
+                    None => return true,
+                },
+            };
+            match doc {
+                Doc::Nil => {}
+                Doc::Token(token) => {
+                    let width = first_line_width(token.token_type);
+                    remaining -= (pending + width) as isize;
+                    pending = 0;
+                    if remaining < 0 {
+                        return false;
+                    }
+                    if token.token_type.height() > 1 {
+                        // the rest of the token is on other lines
+                        return mode == Mode::Break;
+                    }
+                    at_line_start = false;
+                }
+                Doc::Spaces(n) => {
+                    if !at_line_start {
+                        pending = pending.max(*n);
+                    }
+                }
+                Doc::Line if mode == Mode::Flat => {
+                    if !at_line_start {
+                        pending = pending.max(1);
+                    }
+                }
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::HardLine | Doc::BlankLine if mode == Mode::Flat => return false,
+                Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::BlankLine => return true,
+                Doc::Indent(_, doc) | Doc::Hang(_, doc) | Doc::Align(doc) | Doc::Column(_, doc) => {
+                    stack.push((mode, doc))
+                }
+                Doc::Group(_, doc) => stack.push((mode, doc)),
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            }
+        }
+    }
+
+    /// the cost of laying out the commands in `local` and then `rest` up to the end of the
+    /// logical line, which is the next hard line break, or until `budget` commands are laid out.
+    /// groups that do not fit are laid out both ways, each with half of the budget left, taking
+    /// the cheaper one
+    fn cost<'d>(
+        &self,
+        mut cursor: Cursor,
+        mut local: Vec<Command<'d, 'a>>,
+        rest: &[Command<'d, 'a>],
+        mut budget: usize,
+    ) -> usize {
+        let mut cost = 0;
+        let mut rest_len = rest.len();
+        while budget > 0 {
+            budget -= 1;
+            self.searched.set(self.searched.get() + 1);
+            let command = match local.pop() {
+                Some(command) => command,
+                None if rest_len > 0 => {
+                    rest_len -= 1;
+                    rest[rest_len]
+                }
+                None => return cost,
+            };
+            match command.doc {
+                Doc::Nil => {}
+                Doc::Token(token) => {
+                    let col = cursor.token(token.token_type);
+                    let end = col + first_line_width(token.token_type);
+                    let excess = end.saturating_sub(col.max(self.width));
+                    cost = cost.saturating_add(excess * self.excess_penalty);
+                }
+                Doc::Spaces(n) => cursor.space(*n),
+                Doc::Line if command.mode == Mode::Flat => cursor.space(1),
+                Doc::SoftLine if command.mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine => {
+                    if cursor.newlines == 0 {
+                        cursor.line_break();
+                    }
+                    cursor.indent(&command);
+                }
+                Doc::HardLine | Doc::BlankLine if command.mode == Mode::Flat => return usize::MAX,
+                Doc::HardLine | Doc::BlankLine => return cost,
+                Doc::Group(penalty, doc) => {
+                    let flat = Command {
+                        mode: Mode::Flat,
+                        ..command.with(doc)
+                    };
+                    let broken = Command {
+                        mode: Mode::Break,
+                        ..flat
+                    };
+                    if self.fits(cursor, flat, &local, &rest[..rest_len]) {
+                        local.push(flat);
+                    } else if budget < 2 {
+                        cost = cost.saturating_add(*penalty);
+                        local.push(broken);
+                    } else {
+                        let rest = &rest[..rest_len];
+                        let mut flat_local = local.clone();
+                        flat_local.push(flat);
+                        local.push(broken);
+                        let flat_cost = self.cost(cursor, flat_local, rest, budget / 2);
+                        let broken_cost = self
+                            .cost(cursor, local, rest, budget / 2)
+                            .saturating_add(*penalty);
+                        return cost.saturating_add(flat_cost.min(broken_cost));
+                    }
+                }
+                _ => push_nested(cursor, command, &mut local),
+            }
+        }
+        cost
+    }
+}
+
+/// pushes the content of an indentation or concatenation
+fn push_nested<'d, 'a>(cursor: Cursor, command: Command<'d, 'a>, stack: &mut Vec<Command<'d, 'a>>) {
+    match command.doc {
+        Doc::Indent(columns, doc) => stack.push(Command {
+            indent: command.indent + columns,
+            level: command.level + columns,
+            ..command.with(doc)
+        }),
+        Doc::Hang(columns, doc) => stack.push(Command {
+            indent: (cursor.line_indent + columns) as isize,
+            ..command.with(doc)
+        }),
+        Doc::Align(doc) => stack.push(Command {
+            indent: cursor.next_col() as isize,
+            ..command.with(doc)
+        }),
+        Doc::Column(column, doc) => stack.push(Command {
+            indent: *column as isize,
+            level: command.level.min(*column as isize),
+            ..command.with(doc)
+        }),
+        Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| command.with(doc))),
+        _ => unreachable!("not a nested doc"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        reconstruct::reconstruct, Config, Location, ParenType, PunctType, Token, TokenType,
+    };
+
+    use super::{render, Doc, Renderer, SEARCH_BUDGET};
+
+    fn layout(doc: &Doc, column_limit: usize) -> String {
+        let config = Config {
+            column_limit,
+            ..Config::default()
+        };
+        reconstruct(render(doc, &config).tokens.into_iter())
+    }
+
+    fn token(token_type: TokenType<'_>) -> Doc<'_> {
+        Doc::Token(Token::new(token_type, Location::new(0, 0)))
+    }
+
+    fn ident(name: &str) -> Doc<'_> {
+        token(TokenType::Indentifier(name))
+    }
+
+    fn call<'a>(name: &'a str, args: &[&'a str]) -> Doc<'a> {
+        let comma = Doc::Concat(vec![
+            token(TokenType::Punctuation(PunctType::Comma)),
+            Doc::Line,
+        ]);
+        Doc::group(
+            10,
+            Doc::Concat(vec![
+                ident(name),
+                token(TokenType::Paren(ParenType::LParen)),
+                Doc::align(Doc::join(args.iter().map(|arg| ident(arg)), comma)),
+                token(TokenType::Paren(ParenType::RParen)),
+            ]),
+        )
+    }
+
+    /// `name = value` with a break after the `=` costing `penalty`
+    fn assignment<'a>(name: &'a str, value: Doc<'a>, penalty: usize) -> Doc<'a> {
+        Doc::Concat(vec![
+            ident(name),
+            Doc::space(),
+            token(TokenType::Operator(crate::OpType::Assign)),
+            Doc::group(penalty, Doc::indent(4, Doc::Concat(vec![Doc::Line, value]))),
+        ])
+    }
+
+    #[test]
+    fn groups_that_fit_stay_flat() {
+        let doc = call("f", &["a", "b", "c"]);
+        assert_eq!(layout(&doc, 80), "f(a, b, c)\n");
+    }
+
+    #[test]
+    fn groups_that_do_not_fit_break() {
+        let doc = call("function", &["first", "second", "third"]);
+        assert_eq!(
+            layout(&doc, 20),
+            "function(first,\n         second,\n         third)\n"
+        );
+    }
+
+    #[test]
+    fn cheapest_breaks_are_taken() {
+        // breaking after the `=` does not save breaking the arguments
+        let doc = assignment("result", call("function", &["first", "second", "third"]), 5);
+        assert_eq!(
+            layout(&doc, 26),
+            "result = function(first,\n                  second,\n                  third)\n"
+        );
+
+        // but it is cheaper than breaking them
+        let doc = assignment("result", call("function", &["first", "second", "third"]), 5);
+        assert_eq!(
+            layout(&doc, 34),
+            "result =\n    function(first, second, third)\n"
+        );
+
+        // unless it is made more expensive
+        let doc = assignment(
+            "result",
+            call("function", &["first", "second", "third"]),
+            50,
+        );
+        assert_eq!(
+            layout(&doc, 34),
+            "result = function(first,\n                  second,\n                  third)\n"
+        );
+    }
+
+    #[test]
+    fn line_breaks_are_not_doubled() {
+        let doc = Doc::Concat(vec![
+            ident("a"),
+            Doc::HardLine,
+            Doc::HardLine,
+            Doc::space(),
+            ident("b"),
+            Doc::BlankLine,
+            Doc::BlankLine,
+            Doc::Spaces(2),
+            Doc::HardLine,
+            ident("c"),
+        ]);
+        assert_eq!(layout(&doc, 80), "a\nb\n\nc\n");
+    }
+
+    #[test]
+    fn long_lines_are_searched_within_the_budget() {
+        let calls = 500;
+        let comma = Doc::Concat(vec![
+            token(TokenType::Punctuation(PunctType::Comma)),
+            Doc::Line,
+        ]);
+        let doc = Doc::group(
+            0,
+            Doc::join(
+                (0..calls).map(|_| call("function", &["first", "second", "third"])),
+                comma,
+            ),
+        );
+        let config = Config {
+            column_limit: 20,
+            ..Config::default()
+        };
+        let mut renderer = Renderer::new(&config);
+        renderer.run(&doc);
+        // the outer group and each call are compared broken and flat once
+        assert!(renderer.searched.get() <= (calls + 1) * 2 * SEARCH_BUDGET);
+        assert_eq!(
+            renderer
+                .out
+                .iter()
+                .filter(|token| token.token_type == TokenType::Linebreak)
+                .count(),
+            calls * 3 - 1
+        );
+    }
+}
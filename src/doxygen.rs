@@ -0,0 +1,312 @@
+use crate::{
+    ast::{Declarator, Item, Param, TokenIdx, TranslationUnit},
+    Location, ParenType, PunctType, Token, TokenType, Warning, LINE_ENDING,
+};
+
+/// a command starting a line of a doc comment, like `@param[in] len the length`
+struct Tag<'t> {
+    /// the command with its direction, like `@param[in]`
+    command: &'t str,
+    /// the command without its `@` and direction, like `param`
+    kind: &'t str,
+    /// the name of what the command documents, for commands like `@param`
+    name: Option<&'t str>,
+    /// the description on the line of the command
+    description: &'t str,
+}
+
+/// commands whose first word is the name of what they document
+const NAMED: [&str; 6] = ["param", "tparam", "retval", "throw", "throws", "exception"];
+
+/// commands followed by a description
+const DESCRIBED: [&str; 17] = [
+    "brief",
+    "short",
+    "details",
+    "return",
+    "returns",
+    "result",
+    "note",
+    "warning",
+    "attention",
+    "remark",
+    "remarks",
+    "pre",
+    "post",
+    "since",
+    "deprecated",
+    "todo",
+    "see",
+];
+
+/// whether `line` starts with a command like `@brief` or `\param`
+pub(crate) fn is_command(line: &str) -> bool {
+    let mut chars = line.chars();
+    matches!(chars.next(), Some('@' | '\\')) && chars.next().is_some_and(char::is_alphabetic)
+}
+
+/// whether `line` starts or ends a code sample, whose lines are kept as they are
+pub(crate) fn is_code_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix(['@', '\\']).unwrap_or_default();
+    ["code", "endcode", "verbatim", "endverbatim"]
+        .iter()
+        .any(|fence| line.starts_with(fence))
+}
+
+fn tag(line: &str) -> Option<Tag<'_>> {
+    if !is_command(line) {
+        return None;
+    }
+    let command = line.split_whitespace().next()?;
+    let kind = command[1..].split('[').next()?;
+    let rest = line[command.len()..].trim();
+    if NAMED.contains(&kind) {
+        let (name, description) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let name = (!name.is_empty()).then_some(name);
+        let description = description.trim_start();
+        Some(Tag {
+            command,
+            kind,
+            name,
+            description,
+        })
+    } else {
+        DESCRIBED.contains(&kind).then_some(Tag {
+            command,
+            kind,
+            name: None,
+            description: rest,
+        })
+    }
+}
+
+/// the command and name that start the line of a tag
+fn head(tag: &Tag) -> String {
+    match tag.name {
+        Some(name) => format!("{} {name}", tag.command),
+        None => tag.command.to_string(),
+    }
+}
+
+/// the lines of `words` after `head`, wrapped at `room` columns, with the lines after the first
+/// indented to where the description starts
+fn fill(head: String, words: &[&str], room: usize) -> Vec<String> {
+    let indent = " ".repeat(head.chars().count());
+    let mut lines = vec![];
+    let mut line = head;
+    let mut empty = true;
+    for word in words {
+        if !empty && line.chars().count() + 1 + word.chars().count() > room {
+            lines.push(std::mem::replace(&mut line, indent.clone()));
+        }
+        line.push(' ');
+        line.push_str(word);
+        empty = false;
+    }
+    lines.push(line.trim_end().to_string());
+    lines
+}
+
+/// rewrites the lines of a doc comment so that the descriptions of consecutive commands of the
+/// same kind, like a run of `@param`, start at the same column, wrapping them at `room` columns
+/// with the lines continuing a description indented to that column
+///
+/// a description reaches to the next empty line or command, other lines are kept as they are
+pub(crate) fn normalize(content: &[&str], room: usize) -> Vec<String> {
+    let mut out = vec![];
+    let mut code = false;
+    let mut idx = 0;
+    while idx < content.len() {
+        let line = content[idx];
+        if is_code_fence(line) {
+            code = !code;
+        }
+        let Some(kind) = tag(line).filter(|_| !code).map(|tag| tag.kind) else {
+            out.push(line.trim_end().to_string());
+            idx += 1;
+            continue;
+        };
+
+        let mut entries = vec![];
+        while let Some(entry) = content
+            .get(idx)
+            .and_then(|line| tag(line))
+            .filter(|entry| entry.kind == kind)
+        {
+            idx += 1;
+            let mut words: Vec<_> = entry.description.split_whitespace().collect();
+            while let Some(line) = content.get(idx).filter(|line| {
+                !line.trim().is_empty() && tag(line.trim_start()).is_none() && !is_code_fence(line)
+            }) {
+                words.extend(line.split_whitespace());
+                idx += 1;
+            }
+            entries.push((head(&entry), words));
+        }
+        let width = entries
+            .iter()
+            .map(|(head, _)| head.chars().count())
+            .max()
+            .unwrap_or_default();
+        for (head, words) in entries {
+            out.extend(fill(format!("{head:width$}"), &words, room));
+        }
+    }
+    out
+}
+
+/// the doc comment before the declaration of `name`, a `/**` or `/*!` comment or a run of `///`
+/// or `//!` comments
+fn doc_comment<'t, 'a>(tokens: &'t [Token<'a>], name: TokenIdx) -> Option<Vec<&'t Token<'a>>> {
+    let mut idx = name;
+    let last = loop {
+        idx = idx.checked_sub(1)?;
+        match tokens[idx].token_type {
+            TokenType::Comment(_) | TokenType::BlockComment(_) => break idx,
+            TokenType::Punctuation(PunctType::Semicolon)
+            | TokenType::Paren(ParenType::LBrace | ParenType::RBrace)
+            | TokenType::Directive(_) => return None,
+            _ => {}
+        }
+    };
+    match tokens[last].token_type {
+        TokenType::BlockComment(text) => {
+            let doc = text.starts_with(['*', '!']) && !text.starts_with("**");
+            doc.then(|| vec![&tokens[last]])
+        }
+        _ => {
+            let mut comments = vec![];
+            let mut idx = last;
+            while let TokenType::Comment(text) = tokens[idx].token_type {
+                if !text.starts_with(['/', '!']) || text.starts_with("//") {
+                    break;
+                }
+                comments.push(&tokens[idx]);
+                // the comment on the line before, with only the line break between them
+                match idx.checked_sub(2) {
+                    Some(prev) if tokens[prev + 1].token_type == TokenType::Linebreak => idx = prev,
+                    _ => break,
+                }
+            }
+            comments.reverse();
+            (!comments.is_empty()).then_some(comments)
+        }
+    }
+}
+
+/// the lines of the comments of a doc comment without their markers and where each starts
+fn doc_lines<'a>(comments: &[&Token<'a>]) -> Vec<(&'a str, Location)> {
+    let mut lines = vec![];
+    for comment in comments {
+        let (TokenType::Comment(text) | TokenType::BlockComment(text)) = comment.token_type else {
+            continue;
+        };
+        for (row, line) in text.split(LINE_ENDING).enumerate() {
+            let content = line.trim_start().trim_start_matches(['*', '/', '!']).trim();
+            let col = match row {
+                0 => comment.location.col + 2,
+                _ => 0,
+            };
+            let location = Location::new(
+                comment.location.line + row,
+                col + line.find(content).unwrap_or_default(),
+            );
+            lines.push((content, location));
+        }
+    }
+    lines
+}
+
+fn text<'a>(tokens: &[Token<'a>], idx: TokenIdx) -> Option<&'a str> {
+    match tokens[idx].token_type {
+        TokenType::Indentifier(name) => Some(name),
+        _ => None,
+    }
+}
+
+/// a warning for each `@param` in the doc comment of a function that does not name one of its
+/// parameters
+pub(crate) fn warnings(tokens: &[Token], unit: &TranslationUnit) -> Vec<Warning> {
+    let mut warnings = vec![];
+    for item in &unit.items {
+        let declarator: &Declarator = match item {
+            Item::Function(function) => &function.declarator,
+            Item::Declaration(declaration) if declaration.declarators.len() == 1 => {
+                &declaration.declarators[0].declarator
+            }
+            _ => continue,
+        };
+        let (Some(name), Some(params)) = (declarator.name(), declarator.params()) else {
+            continue;
+        };
+        let Some(comments) = doc_comment(tokens, name) else {
+            continue;
+        };
+        let names: Vec<_> = params
+            .params
+            .iter()
+            .filter_map(|param| match param {
+                Param::Declaration { declarator, .. } => declarator.name(),
+                Param::Ellipsis(_) => None,
+            })
+            .filter_map(|idx| text(tokens, idx))
+            .collect();
+        for (line, location) in doc_lines(&comments) {
+            let Some(Tag {
+                command,
+                kind: "param",
+                name: Some(documented),
+                ..
+            }) = tag(line)
+            else {
+                continue;
+            };
+            for documented in documented.split(',') {
+                if documented != "..." && !names.contains(&documented) {
+                    warnings.push(Warning {
+                        location,
+                        message: format!(
+                            "`{command} {documented}` does not name a parameter of `{}`",
+                            text(tokens, name).unwrap_or_default()
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config, Location};
+
+    #[test]
+    fn doc_comments() {
+        let program = "/** @brief Copies bytes.\n * @param dst the destination\n * @param[in] src the source, which has to be long enough\n * @return the number of bytes */\nint copy(char *dst, char *src);\n";
+        let config = Config {
+            column_limit: 48,
+            ..Config::default()
+        };
+        assert_eq!(
+            format_with_config(program, &config).output,
+            "/**\n * @brief Copies bytes.\n * @param dst     the destination\n * @param[in] src the source, which has to be\n *                long enough\n * @return the number of bytes\n */\nint copy(char *dst, char *src);\n"
+        );
+        let program = "/** the count */\nint count;\n";
+        assert_eq!(format_with_config(program, &config).output, program);
+    }
+
+    #[test]
+    fn unknown_parameters() {
+        let program = "/**\n * @param a first\n * @param c second\n */\nint add(int a, int b);\n";
+        let formatted = format_with_config(program, &Config::default());
+        assert_eq!(formatted.output, program);
+        assert_eq!(formatted.warnings.len(), 1);
+        assert_eq!(formatted.warnings[0].location, Location::new(2, 3));
+        assert_eq!(
+            formatted.warnings[0].message,
+            "`@param c` does not name a parameter of `add`"
+        );
+    }
+}
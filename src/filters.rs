@@ -1,4 +1,221 @@
+mod align_backslashes;
+mod align_bitfields;
+mod align_comments;
 mod align_eq;
+mod align_names;
+mod align_tables;
+mod blank_lines;
+mod reflow_comments;
+mod sort_includes;
 
+use std::ops::Range;
 
+pub(crate) use align_backslashes::align_backslashes;
+pub(crate) use align_bitfields::align_bitfields;
+pub(crate) use align_comments::align_comments;
 pub use align_eq::align_eq;
+pub(crate) use align_names::align_names;
+pub(crate) use align_tables::align_tables;
+pub(crate) use blank_lines::blank_lines;
+pub(crate) use reflow_comments::Reflow;
+pub(crate) use sort_includes::sort_includes;
+
+use crate::{doc::Rendered, Config, Location, ParenType, Token, TokenType};
+
+/// the ranges of `tokens` on each line, including the line break ending the line
+pub(crate) fn lines(tokens: &[Token]) -> Vec<Range<usize>> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        if token.token_type == TokenType::Linebreak {
+            lines.push(start..idx + 1);
+            start = idx + 1;
+        }
+    }
+    if start < tokens.len() {
+        lines.push(start..tokens.len());
+    }
+    lines
+}
+
+/// puts the lines of `rendered` in the order of `lines`, which are ranges of its tokens as
+/// returned by [`lines`], or `None` for an empty line
+pub(crate) fn rearrange(rendered: &mut Rendered, lines: &[Option<Range<usize>>]) {
+    let mut tokens = vec![];
+    let mut levels = vec![];
+    let mut row = 0;
+    for line in lines {
+        let Some(line) = line else {
+            tokens.push(Token::new(TokenType::Linebreak, Location::new(row, 0)));
+            levels.push(0);
+            row += 1;
+            continue;
+        };
+        let first_row = rendered.tokens[line.start].location.line;
+        for token in &rendered.tokens[line.clone()] {
+            let mut token = *token;
+            token.location.line = token.location.line - first_row + row;
+            tokens.push(token);
+        }
+        let last_row = rendered.tokens[line.end - 1].location.line;
+        for old in first_row..=last_row {
+            levels.push(rendered.levels.get(old).copied().unwrap_or_default());
+        }
+        row += last_row - first_row + 1;
+    }
+    rendered.tokens = tokens;
+    rendered.levels = levels;
+}
+
+/// moves `tokens`, which are on the same line, to the right by `columns`
+pub(crate) fn shift(tokens: &mut [Token], columns: usize) {
+    for token in tokens {
+        token.location.col += columns;
+    }
+}
+
+/// the lines after `moved` that are lined up inside the parentheses or brackets it leaves open,
+/// like the arguments of a call aligned after its `(`, which move along with it
+fn continuation(tokens: &[Token], moved: Range<usize>) -> Vec<Range<usize>> {
+    let mut open = vec![];
+    for token in &tokens[moved.clone()] {
+        match token.token_type {
+            TokenType::Paren(ParenType::LParen | ParenType::LBrack) => {
+                open.push(token.location.col)
+            }
+            TokenType::Paren(ParenType::RParen | ParenType::RBrack) => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    let Some(&paren) = open.first() else {
+        return vec![];
+    };
+    let mut lines = vec![];
+    let mut depth = open.len();
+    let mut start = moved.end;
+    while depth > 0 && start < tokens.len() {
+        let end = tokens[start..]
+            .iter()
+            .position(|token| token.token_type == TokenType::Linebreak)
+            .map_or(tokens.len(), |end| start + end + 1);
+        for token in &tokens[start..end] {
+            match token.token_type {
+                TokenType::Paren(ParenType::LParen | ParenType::LBrack) => depth += 1,
+                TokenType::Paren(ParenType::RParen | ParenType::RBrack) => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+        }
+        // lines indented from the start of the statement rather than aligned stay where they are
+        if tokens[start].location.col > paren {
+            lines.push(start..end);
+        }
+        start = end;
+    }
+    lines
+}
+
+/// moves `moved`, the rest of a line, to the right by `columns` along with its continuation
+/// lines
+fn shift_lines(tokens: &mut [Token], moved: Range<usize>, columns: usize) {
+    for line in continuation(tokens, moved.clone()) {
+        shift(&mut tokens[line], columns);
+    }
+    shift(&mut tokens[moved], columns);
+}
+
+/// the column at which the widest of `moved`, the rest of a line, and its continuation lines
+/// ends
+fn reach(tokens: &[Token], moved: Range<usize>) -> usize {
+    let end = |line: Range<usize>| {
+        tokens[line]
+            .iter()
+            .filter(|token| token.token_type != TokenType::Linebreak)
+            .map(|token| token.location.col + token.token_type.width())
+            .max()
+            .unwrap_or_default()
+    };
+    let lines = continuation(tokens, moved.clone());
+    lines.into_iter().map(end).fold(end(moved), usize::max)
+}
+
+/// a column on a line that is lined up with the same column on the lines around it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Anchor {
+    /// what has to be at the same column on consecutive lines for them to be aligned, like the
+    /// start of the line
+    pub(crate) key: usize,
+    /// the first token that is moved
+    pub(crate) from: usize,
+    /// the column that is lined up, at or after the start of `from`
+    pub(crate) col: usize,
+}
+
+/// lines up the anchors of runs of consecutive lines that have an anchor with the same key, by
+/// moving the rest of the line after each anchor to the right
+///
+/// a run also ends where lining up the next line would move a line past the column limit
+pub(crate) fn align_runs(
+    tokens: &mut [Token],
+    config: &Config,
+    anchor: impl Fn(&[Token], Range<usize>) -> Option<Anchor>,
+) {
+    let mut run: Vec<(Range<usize>, Anchor)> = vec![];
+    for line in lines(tokens) {
+        let Some(next) = anchor(tokens, line.clone()) else {
+            align(tokens, &run);
+            run.clear();
+            continue;
+        };
+        let continues = run.last().is_some_and(|(last, anchor)| {
+            let last = tokens[last.start].location.line;
+            tokens[line.start].location.line == last + 1 && anchor.key == next.key
+        });
+        if !continues || !fits(tokens, &run, &(line.clone(), next), config) {
+            align(tokens, &run);
+            run.clear();
+        }
+        // aligning the run may have moved the line along with the line it continues
+        match anchor(tokens, line.clone()) {
+            Some(next) => run.push((line, next)),
+            None => continue,
+        }
+    }
+    align(tokens, &run);
+}
+
+/// the column the anchors of `lines` are lined up at
+fn column(lines: &[(Range<usize>, Anchor)]) -> usize {
+    lines
+        .iter()
+        .map(|(_, anchor)| anchor.col)
+        .max()
+        .unwrap_or_default()
+}
+
+/// whether lining up `next` with `run` keeps every line it moves within the column limit
+fn fits(
+    tokens: &[Token],
+    run: &[(Range<usize>, Anchor)],
+    next: &(Range<usize>, Anchor),
+    config: &Config,
+) -> bool {
+    let column = column(run).max(next.1.col);
+    run.iter().chain([next]).all(|(line, anchor)| {
+        let columns = column - anchor.col;
+        columns == 0 || reach(tokens, anchor.from..line.end) + columns <= config.column_limit
+    })
+}
+
+fn align(tokens: &mut [Token], run: &[(Range<usize>, Anchor)]) {
+    if run.len() < 2 {
+        return;
+    }
+    let column = column(run);
+    for (line, anchor) in run {
+        shift_lines(tokens, anchor.from..line.end, column - anchor.col);
+    }
+}
@@ -0,0 +1,124 @@
+use std::ops::Range;
+
+use crate::{Backslashes, Config, Token, TokenType, LINE_ENDING};
+
+use super::lines;
+
+/// a line ending with a `\`
+struct Continued {
+    line: Range<usize>,
+    /// the `\`
+    backslash: usize,
+    /// the column after the last token before the `\`, if there is one
+    end: Option<usize>,
+}
+
+fn end_col(token: &Token) -> usize {
+    match token.token_type.to_str().rsplit_once(LINE_ENDING) {
+        Some((_, tail)) => tail.chars().count(),
+        None => token.location.col + token.token_type.width(),
+    }
+}
+
+fn continued(tokens: &[Token], line: Range<usize>) -> Option<Continued> {
+    let backslash = line
+        .clone()
+        .rev()
+        .find(|idx| tokens[*idx].token_type != TokenType::Linebreak)?;
+    if tokens[backslash].token_type != TokenType::LineContinuation {
+        return None;
+    }
+    let end = (backslash > line.start).then(|| end_col(&tokens[backslash - 1]));
+    Some(Continued {
+        line,
+        backslash,
+        end,
+    })
+}
+
+/// puts the `\` ending the lines of multi-line directives where `config` says
+///
+/// the lines of a directive are aligned on their own, so that the column of one macro does not
+/// depend on the ones around it
+pub(crate) fn align_backslashes(tokens: &mut [Token], config: &Config) {
+    let mut directive: Vec<Continued> = vec![];
+    for line in lines(tokens) {
+        match continued(tokens, line) {
+            Some(next) => directive.push(next),
+            None => {
+                align(tokens, &directive, config);
+                directive.clear();
+            }
+        }
+    }
+    align(tokens, &directive, config);
+}
+
+fn align(tokens: &mut [Token], directive: &[Continued], config: &Config) {
+    let aligned = directive
+        .iter()
+        .filter_map(|continued| continued.end)
+        .max()
+        .map(|end| end + 1);
+    for continued in directive {
+        let col = match config.alignment.backslashes {
+            Backslashes::Space => continued.end.map(|end| end + 1),
+            Backslashes::Aligned => aligned,
+            Backslashes::ColumnLimit => {
+                aligned.map(|col| col.max(config.column_limit.saturating_sub(1)))
+            }
+        };
+        let Some(col) = col else {
+            continue;
+        };
+        for (offset, idx) in (continued.backslash..continued.line.end).enumerate() {
+            tokens[idx].location.col = col + offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Alignment, Backslashes, Config};
+
+    fn format(program: &str, backslashes: Backslashes) -> String {
+        let config = Config {
+            column_limit: 40,
+            alignment: Alignment {
+                backslashes,
+                ..Alignment::default()
+            },
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn backslashes() {
+        let program = "#define SWAP(a, b) \\\n    do { \\\n        int t = a;   \\\n        a = b; b = t; \\\n    } while (0)\n#define ONE 1 \\\n    + 2\n";
+        assert_eq!(
+            format(program, Backslashes::Space),
+            "#define SWAP(a, b) \\\n    do { \\\n        int t = a; \\\n        a = b; b = t; \\\n    } while (0)\n#define ONE 1 \\\n    + 2\n"
+        );
+        assert_eq!(
+            format(program, Backslashes::Aligned),
+            "#define SWAP(a, b)    \\\n    do {              \\\n        int t = a;    \\\n        a = b; b = t; \\\n    } while (0)\n#define ONE 1 \\\n    + 2\n"
+        );
+        assert_eq!(
+            format(program, Backslashes::ColumnLimit),
+            format!(
+                "#define SWAP(a, b){0:21}\\\n    do {{{0:31}\\\n        int t = a;{0:21}\\\n        a = b; b = t;{0:18}\\\n    }} while (0)\n#define ONE 1{0:26}\\\n    + 2\n",
+                ""
+            )
+        );
+    }
+
+    #[test]
+    fn directives_are_aligned_after_indentation() {
+        let program = "void f(void)\n{\n#define A(x)  \\\n  x +  \\\n  1\n    g();\n}\n";
+        assert_eq!(
+            format(program, Backslashes::Aligned),
+            "void f(void)\n{\n#define A(x) \\\n  x +        \\\n  1\n    g();\n}\n"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+
+use crate::{Config, Token};
+
+use super::{align_runs, Anchor};
+
+/// aligns the `:` of the bitfields declared on consecutive lines that start at the same column,
+/// and the widths after them
+///
+/// `colons` are the source offsets of the `:` of each bitfield. a line declaring several
+/// bitfields is aligned by the first one
+pub(crate) fn align_bitfields(tokens: &mut [Token], colons: &[usize], config: &Config) {
+    let colons: HashSet<usize> = colons.iter().copied().collect();
+    align_runs(tokens, config, |tokens, line| {
+        let colon = line.clone().find(|idx| {
+            !tokens[*idx].token_type.is_trivia() && colons.contains(&tokens[*idx].offset)
+        })?;
+        Some(Anchor {
+            key: tokens[line.start].location.col,
+            from: colon,
+            col: tokens[colon].location.col,
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, BitfieldSpacing, Config, Spacing};
+
+    fn format(program: &str, bitfield_colons: BitfieldSpacing) -> String {
+        let config = Config {
+            spacing: Spacing {
+                bitfield_colons,
+                ..Spacing::default()
+            },
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn bitfields() {
+        let program = "struct reg {\n    uint32_t enable : 1;\n    uint32_t mode:3;\n    \
+                       unsigned : 4;\n    unsigned long long reserved : 24;\n    int x;\n};\n";
+        assert_eq!(
+            format(program, BitfieldSpacing::Both),
+            "struct reg {\n    uint32_t           enable   : 1;\n    uint32_t           mode     : 3;\n    \
+             unsigned                    : 4;\n    unsigned long long reserved : 24;\n    \
+             int                x;\n};\n"
+        );
+        assert_eq!(
+            format(program, BitfieldSpacing::None),
+            "struct reg {\n    uint32_t           enable  :1;\n    uint32_t           mode    :3;\n    \
+             unsigned                   :4;\n    unsigned long long reserved:24;\n    \
+             int                x;\n};\n"
+        );
+    }
+
+    #[test]
+    fn unnamed_bitfields() {
+        let program = "struct reg {\n    unsigned : 4;\n};\nstruct flags {\n    uint32_t:4;\n};\n";
+        assert_eq!(
+            format(program, BitfieldSpacing::Both),
+            "struct reg {\n    unsigned : 4;\n};\nstruct flags {\n    uint32_t : 4;\n};\n"
+        );
+        assert_eq!(
+            format(program, BitfieldSpacing::After),
+            "struct reg {\n    unsigned: 4;\n};\nstruct flags {\n    uint32_t: 4;\n};\n"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::ops::Range;
+
+use crate::{Config, Token, TokenType};
+
+use super::{align, lines, Anchor};
+
+/// a comment ending a line of code
+struct Trailing {
+    line: Range<usize>,
+    anchor: Anchor,
+    width: usize,
+}
+
+/// the comment at the end of a line that has code before it, if it fits on one line
+fn trailing(tokens: &[Token], line: Range<usize>) -> Option<Trailing> {
+    let last = line
+        .clone()
+        .rev()
+        .find(|idx| tokens[*idx].token_type != TokenType::Linebreak)?;
+    let comment = &tokens[last].token_type;
+    if !matches!(comment, TokenType::Comment(_) | TokenType::BlockComment(_))
+        || comment.height() > 1
+    {
+        return None;
+    }
+    let code = tokens[line.start..last]
+        .iter()
+        .any(|token| !token.token_type.is_trivia());
+    code.then(|| Trailing {
+        line,
+        anchor: Anchor {
+            key: 0,
+            from: last,
+            col: tokens[last].location.col,
+        },
+        width: comment.width(),
+    })
+}
+
+/// whether `next` can be aligned with the comments of `run`, without moving any of them by
+/// more than the largest shift or past the column limit
+fn fits(run: &[Trailing], next: &Trailing, config: &Config) -> bool {
+    let cols = || run.iter().chain([next]).map(|comment| comment.anchor.col);
+    let column = cols().max().unwrap_or_default();
+    let least = cols().min().unwrap_or_default();
+    column - least <= config.alignment.max_comment_shift
+        && run.iter().chain([next]).all(|comment| {
+            comment.anchor.col == column || column + comment.width <= config.column_limit
+        })
+}
+
+/// aligns the comments at the end of consecutive lines
+///
+/// a run of lines ends at a line without a trailing comment and where aligning the next comment
+/// would move a comment by more than `max_comment_shift` columns or past the column limit
+pub(crate) fn align_comments(tokens: &mut [Token], config: &Config) {
+    let mut run: Vec<Trailing> = vec![];
+    for line in lines(tokens) {
+        let Some(next) = trailing(tokens, line) else {
+            align_run(tokens, &mut run);
+            continue;
+        };
+        let continues = run.last().is_some_and(|last| {
+            let last = tokens[last.line.start].location.line;
+            tokens[next.line.start].location.line == last + 1
+        });
+        if !continues || !fits(&run, &next, config) {
+            align_run(tokens, &mut run);
+        }
+        run.push(next);
+    }
+    align_run(tokens, &mut run);
+}
+
+fn align_run(tokens: &mut [Token], run: &mut Vec<Trailing>) {
+    let lines: Vec<_> = run
+        .drain(..)
+        .map(|comment| (comment.line, comment.anchor))
+        .collect();
+    align(tokens, &lines);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config};
+
+    fn format(program: &str) -> String {
+        format_with_config(program, &Config::default()).output
+    }
+
+    #[test]
+    fn trailing_comments() {
+        assert_eq!(
+            format("void f(void)\n{\n    x = 1; // reset\n    longer_name = 2; /* count */\n}\n"),
+            "void f(void)\n{\n    x           = 1; // reset\n    longer_name = 2; /* count */\n}\n"
+        );
+        assert_eq!(
+            format("void f(void)\n{\n    f(1); // one\n    g(longer, 2); // two\n\n    h(); // three\n}\n"),
+            "void f(void)\n{\n    f(1);         // one\n    g(longer, 2); // two\n\n    h(); // three\n}\n"
+        );
+    }
+
+    #[test]
+    fn runs_are_broken() {
+        let program = "void f(void)\n{\n    a(); // a\n    b(); // b\n    \
+                       a_very_long_function_name_that_is_far_away(); // c\n    d(); // d\n}\n";
+        assert_eq!(
+            format(program),
+            "void f(void)\n{\n    a(); // a\n    b(); // b\n    \
+             a_very_long_function_name_that_is_far_away(); // c\n    d(); // d\n}\n"
+        );
+        let program =
+            "void f(void)\n{\n    a(); // a comment that is much too long to be moved any further to the right\n    \
+                       bcdefghijk(); // b\n}\n";
+        assert_eq!(format(program), program);
+    }
+}
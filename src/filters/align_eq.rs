@@ -1,29 +1,112 @@
-use crate::Token;
+use std::ops::Range;
 
-// super lazy implementation, change later
-pub fn align_eq(mut tokens: &mut [Token]) {
-    let mut lines = vec![];
+use crate::{parser::is_assignment, Config, ParenType, Token, TokenType};
 
-    while !tokens.is_empty() {
-        let tmp;
-        (tmp, tokens) = tokens.split_at_mut(1);
+use super::{align_runs, Anchor};
 
-        lines.push(tmp);
+/// the first assignment operator on a line that is not inside parentheses or brackets, unless
+/// the value is on the next line
+fn assignment(tokens: &[Token], line: Range<usize>) -> Option<usize> {
+    let mut depth = 0;
+    for idx in line.clone() {
+        match tokens[idx].token_type {
+            TokenType::Paren(ParenType::LParen | ParenType::LBrack) => depth += 1,
+            TokenType::Paren(ParenType::RParen | ParenType::RBrack) => depth -= 1,
+            TokenType::Operator(op) if depth == 0 && is_assignment(op) => {
+                let value = tokens[idx + 1..line.end]
+                    .iter()
+                    .any(|token| !token.token_type.is_trivia());
+                return value.then_some(idx);
+            }
+            _ => {}
+        }
     }
-    todo!()
+    None
+}
+
+/// aligns the assignment operators of consecutive lines that start at the same column
+///
+/// a run of lines ends at an empty line, at a line without an assignment, such as a comment,
+/// where the indentation changes and where lining up the next line would move a line past the
+/// column limit. operators are lined up by their last character, so that `=` lines up with `+=`
+pub fn align_eq(tokens: &mut [Token], config: &Config) {
+    align_runs(tokens, config, |tokens, line| {
+        let op = assignment(tokens, line.clone())?;
+        Some(Anchor {
+            key: tokens[line.start].location.col,
+            from: op,
+            col: tokens[op].location.col + tokens[op].token_type.width(),
+        })
+    });
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::Tokenizer;
+    use crate::{format_with_config, Alignment, Config};
+
+    fn format(program: &str) -> String {
+        let config = Config {
+            alignment: Alignment {
+                assignments: true,
+                declarations: false,
+                ..Alignment::default()
+            },
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
 
     #[test]
-    fn hi() {
-        let program = "abc;\n       ed;";
-        let tokenizer = Tokenizer::new(program);
+    fn consecutive_assignments() {
+        assert_eq!(
+            format("void f(void)\n{\n    int a = 1;\n    long bc = 2;\n    x += 3;\n}\n"),
+            "void f(void)\n{\n    int a   = 1;\n    long bc = 2;\n    x      += 3;\n}\n"
+        );
+    }
 
+    #[test]
+    fn runs_are_broken() {
+        let program = "void f(void)\n{\n    a = 1;\n    bcd = 2;\n\n    e = 3;\n    fg = 4;\n    \
+                       // comment\n    h = 5;\n    if (x)\n        ijk = 6;\n    l = f(m = 7);\n}\n";
+        assert_eq!(
+            format(program),
+            "void f(void)\n{\n    a   = 1;\n    bcd = 2;\n\n    e  = 3;\n    fg = 4;\n    \
+             // comment\n    h = 5;\n    if (x)\n        ijk = 6;\n    l = f(m = 7);\n}\n"
+        );
+    }
 
+    #[test]
+    fn wrapped_statements() {
+        let program =
+            "void f(void)\n{\n    yyyyyy = 1;\n    x = some_function_name(first_argument, \
+                       second_argument, third_argument, fourth);\n}\n";
+        assert_eq!(
+            format(program),
+            concat!(
+                "void f(void)\n{\n    yyyyyy = 1;\n",
+                "    x      = some_function_name(first_argument,\n",
+                "                                second_argument,\n",
+                "                                third_argument,\n",
+                "                                fourth);\n}\n"
+            )
+        );
+    }
 
+    #[test]
+    fn column_limit() {
+        let program = "void f(void)\n{\n    a_very_long_variable_name_here = 1;\n    \
+                       x = some_function(argument_one, argument_two, argument_three, arg4);\n}\n";
+        assert_eq!(format(program), program);
+        let program = "void f(void)\n{\n    a_very_long_variable_name_here = 1;\n    \
+                       x = some_function(argument_one, argument_two, argument_three, arg4);\n    \
+                       bcd = 2;\n}\n";
+        assert_eq!(
+            format(program),
+            concat!(
+                "void f(void)\n{\n    a_very_long_variable_name_here = 1;\n",
+                "    x   = some_function(argument_one, argument_two, argument_three, arg4);\n",
+                "    bcd = 2;\n}\n"
+            )
+        );
     }
 }
-
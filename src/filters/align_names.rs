@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::{format::DeclaredName, Config, ParenType, Token, TokenType};
+
+use super::{align_runs, Anchor};
+
+/// aligns the names declared on consecutive lines whose types start at the same column
+///
+/// the pointers and parentheses before a name move along with it. a line declaring several
+/// names is aligned by the first one, unless it ends inside parentheses, where it is aligned
+/// by the last one, which is the first of the parameters on the lines that follow. a run ends
+/// where lining up the next line would move a line past the column limit
+pub(crate) fn align_names(tokens: &mut [Token], names: &[DeclaredName], config: &Config) {
+    let names: HashMap<usize, &DeclaredName> = names.iter().map(|name| (name.name, name)).collect();
+    align_runs(tokens, config, |tokens, line| {
+        let find = |offset| {
+            line.clone()
+                .find(|idx| tokens[*idx].offset == offset && !tokens[*idx].token_type.is_trivia())
+        };
+        let mut anchors = line.clone().filter_map(|idx| {
+            if tokens[idx].token_type.is_trivia() {
+                return None;
+            }
+            let name = names.get(&tokens[idx].offset)?;
+            Some(Anchor {
+                key: tokens[find(name.ty)?].location.col,
+                from: find(name.start)?,
+                col: tokens[idx].location.col,
+            })
+        });
+
+        let mut depth = 0;
+        for token in &tokens[line.clone()] {
+            match token.token_type {
+                TokenType::Paren(ParenType::LParen) => depth += 1,
+                TokenType::Paren(ParenType::RParen) => depth -= 1,
+                _ => {}
+            }
+        }
+        match depth > 0 {
+            true => anchors.next_back(),
+            false => anchors.next(),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config, PointerAlignment, Wrapping};
+
+    fn format(program: &str) -> String {
+        format_with_config(program, &Config::default()).output
+    }
+
+    #[test]
+    fn declarations() {
+        assert_eq!(
+            format("int c = 3;\nunsigned char *name = 0;\nlong values[4];\n\nint d;\n"),
+            "int            c    = 3;\nunsigned char *name = 0;\nlong           values[4];\n\nint d;\n"
+        );
+    }
+
+    #[test]
+    fn pointers_attached_to_the_type() {
+        let config = Config {
+            pointer_alignment: PointerAlignment::Left,
+            ..Config::default()
+        };
+        assert_eq!(
+            format_with_config("int a;\nchar **name;\n", &config).output,
+            "int    a;\nchar** name;\n"
+        );
+        assert_eq!(
+            format_with_config("int x;\nint (*fp)(int *);\n", &config).output,
+            "int   x;\nint (*fp)(int*);\n"
+        );
+    }
+
+    #[test]
+    fn wrapped_declarations() {
+        assert_eq!(
+            format(
+                "struct point p = 1;\nconst char *s = g(aaaaaaaaaaaaaaaaaaaaa, \
+                 bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb, ccccccccccccccccccccc, d);\n"
+            ),
+            concat!(
+                "struct point p = 1;\n",
+                "const char  *s = g(aaaaaaaaaaaaaaaaaaaaa,\n",
+                "                   bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb,\n",
+                "                   ccccccccccccccccccccc,\n",
+                "                   d);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn packed_parameters() {
+        let config = Config {
+            parameters: Wrapping {
+                bin_pack: true,
+                align_after_paren: false,
+            },
+            ..Config::default()
+        };
+        assert_eq!(
+            format_with_config(
+                "int function_with_a_long_name(int first, const char *second, long third, \
+                 unsigned fourth, int fifth, const char *sixth);\n",
+                &config
+            )
+            .output,
+            "int function_with_a_long_name(\n    int first, const char *second, long third, \
+             unsigned fourth, int fifth,\n    const char *sixth\n);\n"
+        );
+    }
+
+    #[test]
+    fn members_and_parameters() {
+        assert_eq!(
+            format(
+                "struct s { int a; const char *name; };\nint function_with_a_long_name(int first, \
+                 const char *second, long third, unsigned fourth);\n"
+            ),
+            "struct s {\n    int         a;\n    const char *name;\n};\n\
+             int function_with_a_long_name(int         first,\n                              \
+             const char *second,\n                              long        third,\n\
+             \x20                             unsigned    fourth);\n"
+        );
+    }
+
+    #[test]
+    fn column_limit() {
+        assert_eq!(
+            format(
+                "struct a_very_long_struct_type_name_for_testing *p;\n\
+                 int x = compute_value(first_argument, second_argument, third_argument);\n\
+                 long y;\n"
+            ),
+            "struct a_very_long_struct_type_name_for_testing *p;\n\
+             int  x = compute_value(first_argument, second_argument, third_argument);\n\
+             long y;\n"
+        );
+    }
+}
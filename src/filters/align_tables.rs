@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use crate::{Config, OpType, ParenType, PunctType, Token, TokenType};
+
+/// a braced initializer on a line of its own, like `{ "name", 0x10, FLAG_A },`
+struct Row {
+    line: Range<usize>,
+    /// the tokens of each element, without the comma following it
+    cells: Vec<Range<usize>>,
+    /// the closing brace
+    close: usize,
+}
+
+fn is_number(token_type: TokenType) -> bool {
+    match token_type {
+        TokenType::Const(text) => text.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.'),
+        _ => false,
+    }
+}
+
+/// whether the tokens of a cell are a number, which is aligned to the right
+fn is_numeric(cell: &[Token]) -> bool {
+    match cell {
+        [number] => is_number(number.token_type),
+        [sign, number] => {
+            matches!(
+                sign.token_type,
+                TokenType::Operator(OpType::Sub | OpType::Plus)
+            ) && is_number(number.token_type)
+        }
+        _ => false,
+    }
+}
+
+fn end_col(token: &Token) -> usize {
+    token.location.col + token.token_type.width()
+}
+
+fn row(tokens: &[Token], line: Range<usize>) -> Option<Row> {
+    if tokens[line.start].token_type != TokenType::Paren(ParenType::LBrace) {
+        return None;
+    }
+    let mut cells = vec![];
+    let mut cell = line.start + 1;
+    let mut depth = 0;
+    let mut close = None;
+    for (idx, token) in tokens.iter().enumerate().take(line.end).skip(line.start) {
+        let token_type = token.token_type;
+        if token_type.height() > 1 {
+            return None;
+        }
+        match token_type {
+            TokenType::Paren(ParenType::LParen | ParenType::LBrack | ParenType::LBrace) => {
+                depth += 1
+            }
+            TokenType::Paren(ParenType::RParen | ParenType::RBrack) => depth -= 1,
+            TokenType::Paren(ParenType::RBrace) => {
+                depth -= 1;
+                if depth == 0 {
+                    cells.push(cell..idx);
+                    close = Some(idx);
+                    break;
+                }
+            }
+            TokenType::Punctuation(PunctType::Comma) if depth == 1 => {
+                cells.push(cell..idx);
+                cell = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    let close = close?;
+    let rest_is_trivia = tokens[close + 1..line.end].iter().all(|token| {
+        token.token_type.is_trivia() || token.token_type == TokenType::Punctuation(PunctType::Comma)
+    });
+    let cells_are_filled = cells.iter().all(|cell| !cell.is_empty());
+    (rest_is_trivia && cells_are_filled).then_some(Row { line, cells, close })
+}
+
+/// lines up the elements of the braced initializers on consecutive lines that start at the same
+/// column and have the same number of elements, like the rows of a table
+///
+/// columns of numbers are aligned to the right and everything else to the left. a table
+/// that would not fit in the column limit is left as it is
+pub(crate) fn align_tables(tokens: &mut [Token], config: &Config) {
+    let mut table: Vec<Row> = vec![];
+    for line in super::lines(tokens) {
+        let Some(next) = row(tokens, line) else {
+            align(tokens, &table, config);
+            table.clear();
+            continue;
+        };
+        let continues = table.last().is_some_and(|last| {
+            let (last, next) = (&tokens[last.line.start], &tokens[next.line.start]);
+            next.location.line == last.location.line + 1 && next.location.col == last.location.col
+        }) && table[0].cells.len() == next.cells.len();
+        if !continues {
+            align(tokens, &table, config);
+            table.clear();
+        }
+        table.push(next);
+    }
+    align(tokens, &table, config);
+}
+
+fn align(tokens: &mut [Token], table: &[Row], config: &Config) {
+    if table.len() < 2 || table[0].cells.len() < 2 {
+        return;
+    }
+    let width = |tokens: &[Token], cell: &Range<usize>| {
+        end_col(&tokens[cell.end - 1]) - tokens[cell.start].location.col
+    };
+    let mut columns = vec![];
+    let mut col = tokens[table[0].line.start].location.col + 2;
+    for column in 0..table[0].cells.len() {
+        let widest = table
+            .iter()
+            .map(|row| width(tokens, &row.cells[column]))
+            .max()
+            .unwrap_or_default();
+        let numeric = table
+            .iter()
+            .all(|row| is_numeric(&tokens[row.cells[column].clone()]));
+        columns.push((col, widest, numeric));
+        col += widest + 2;
+    }
+    let close = col - 1;
+
+    let fits = table.iter().all(|row| {
+        let last = row
+            .line
+            .clone()
+            .rev()
+            .find(|idx| tokens[*idx].token_type != TokenType::Linebreak);
+        let end = last.map(|idx| end_col(&tokens[idx])).unwrap_or_default();
+        end + close - tokens[row.close].location.col <= config.column_limit
+    });
+    if !fits {
+        return;
+    }
+
+    for row in table {
+        let mut moves = vec![];
+        for (cell, (col, widest, numeric)) in row.cells.iter().zip(&columns) {
+            let padding = match numeric {
+                true => widest - width(tokens, cell),
+                false => 0,
+            };
+            moves.push((cell.start, col + padding));
+        }
+        moves.push((row.close, close));
+        moves.push((row.line.end, 0));
+        // each cell moves along with the comma after it, and the closing brace with the rest
+        // of the line
+        for pair in moves.windows(2) {
+            let [(from, col), (to, _)] = *pair else {
+                unreachable!()
+            };
+            let old = tokens[from].location.col;
+            for token in &mut tokens[from..to] {
+                token.location.col = token.location.col + col - old;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config};
+
+    fn format(program: &str, column_limit: usize) -> String {
+        let config = Config {
+            column_limit,
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn tables() {
+        let program = "struct entry entries[] = {\n    { \"name\", 0x10, FLAG_A },\n    { \"longer name\", 7, FLAG_B | FLAG_C },\n    { \"x\", -100, 0 },\n};\n";
+        assert_eq!(
+            format(program, 80),
+            "struct entry entries[] = {\n    { \"name\",        0x10, FLAG_A          },\n    { \"longer name\",    7, FLAG_B | FLAG_C },\n    { \"x\",           -100, 0               },\n};\n"
+        );
+    }
+
+    #[test]
+    fn tables_that_do_not_fit_are_kept() {
+        let program = "int table[][3] = {\n    { 1, 2, 3 },\n    { 100000, 2, 3 },\n    { 1, 200000, 3 },\n};\n";
+        assert_eq!(
+            format(program, 80),
+            "int table[][3] = {\n    {      1,      2, 3 },\n    { 100000,      2, 3 },\n    {      1, 200000, 3 },\n};\n"
+        );
+        assert_eq!(format(program, 24), program);
+    }
+}
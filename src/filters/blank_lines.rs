@@ -0,0 +1,167 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::{doc::Rendered, format::Marks, Config, ParenType, Token, TokenType, LINE_ENDING};
+
+use super::{lines, rearrange};
+
+/// a line with tokens on it, which may span several rows if a token does
+struct Line {
+    tokens: Range<usize>,
+    /// the number of empty lines before it
+    blanks: usize,
+}
+
+/// the last token on a line that is not a line break or a comment
+fn last_code(tokens: &[Token], line: &Range<usize>) -> Option<usize> {
+    line.clone().rev().find(|idx| {
+        !matches!(
+            tokens[*idx].token_type,
+            TokenType::Linebreak | TokenType::Comment(_) | TokenType::BlockComment(_)
+        )
+    })
+}
+
+/// the number of empty lines in `source` between the tokens `prev` and `next`
+fn source_blanks(source: &str, prev: &Token, next: &Token) -> usize {
+    let end = prev.offset + prev.token_type.len();
+    match source.get(end..next.offset) {
+        Some(between) => between.matches(LINE_ENDING).count().saturating_sub(1),
+        None => 0,
+    }
+}
+
+/// sets the number of empty lines between the lines of `rendered`
+///
+/// the layout keeps at most one empty line where there are some in the source, and none at the
+/// edges of bodies. up to `max` of the empty lines in `source` are kept instead, and the edges of
+/// bodies keep theirs unless they are trimmed. empty lines are then added between function
+/// definitions and after declarations, as `marks` locates them
+pub(crate) fn blank_lines(rendered: &mut Rendered, source: &str, marks: &Marks, config: &Config) {
+    let options = &config.blank_lines;
+    let function_ends: HashSet<usize> = marks.function_ends.iter().copied().collect();
+    let declaration_ends: HashSet<usize> = marks.declaration_ends.iter().copied().collect();
+    let tokens = &rendered.tokens;
+
+    let mut content: Vec<Line> = vec![];
+    let mut blanks = 0;
+    for line in lines(tokens) {
+        match tokens[line.start].token_type {
+            TokenType::Linebreak => blanks += 1,
+            _ => {
+                content.push(Line {
+                    tokens: line,
+                    blanks,
+                });
+                blanks = 0;
+            }
+        }
+    }
+
+    for i in 1..content.len() {
+        let (prev, line) = (&content[i - 1].tokens, &content[i].tokens);
+        let first = &tokens[line.start];
+        let last = last_code(tokens, prev).map(|idx| &tokens[idx]);
+        let opens = last.is_some_and(|last| last.token_type == TokenType::Paren(ParenType::LBrace));
+        let closes = first.token_type == TokenType::Paren(ParenType::RBrace);
+        let edge = opens || closes;
+
+        let mut blanks = content[i].blanks;
+        if blanks > 0 || (edge && !options.trim_bodies) {
+            let prev = prev
+                .clone()
+                .rev()
+                .find(|idx| tokens[*idx].token_type != TokenType::Linebreak);
+            if let Some(prev) = prev {
+                blanks = blanks.max(source_blanks(source, &tokens[prev], first));
+            }
+        }
+        if edge && options.trim_bodies {
+            blanks = 0;
+        }
+        blanks = blanks.min(options.max);
+        if let Some(last) = last {
+            if options.after_declarations && declaration_ends.contains(&last.offset) {
+                blanks = blanks.max(1);
+            }
+            if function_ends.contains(&last.offset) {
+                blanks = blanks.max(options.between_functions);
+            }
+        }
+        content[i].blanks = blanks;
+    }
+
+    let mut lines = vec![];
+    for line in content {
+        lines.extend((0..line.blanks).map(|_| None));
+        lines.push(Some(line.tokens));
+    }
+    rearrange(rendered, &lines);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, BlankLines, Config};
+
+    fn format(program: &str, blank_lines: BlankLines) -> String {
+        let config = Config {
+            blank_lines,
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn empty_lines_in_a_row() {
+        let program = "int a;\n\n\n\nint b;\n// c\n\n\nint c;\n";
+        let format = |max| {
+            format(
+                program,
+                BlankLines {
+                    max,
+                    ..BlankLines::default()
+                },
+            )
+        };
+        assert_eq!(format(0), "int a;\nint b;\n// c\nint c;\n");
+        assert_eq!(format(1), "int a;\n\nint b;\n// c\n\nint c;\n");
+        assert_eq!(format(2), "int a;\n\n\nint b;\n// c\n\n\nint c;\n");
+    }
+
+    #[test]
+    fn edges_of_bodies() {
+        let program = "void f(void)\n{\n\n    g();\n\n\n}\nstruct s {\n\n    int a;\n};\n";
+        assert_eq!(
+            format(program, BlankLines::default()),
+            "void f(void)\n{\n    g();\n}\nstruct s {\n    int a;\n};\n"
+        );
+        assert_eq!(
+            format(
+                program,
+                BlankLines {
+                    trim_bodies: false,
+                    ..BlankLines::default()
+                }
+            ),
+            "void f(void)\n{\n\n    g();\n\n}\nstruct s {\n\n    int a;\n};\n"
+        );
+    }
+
+    #[test]
+    fn added_empty_lines() {
+        let program =
+            "void f(void)\n{\n    int a;\n    int b;\n    g();\n    int c;\n\n    h();\n}\n\
+                       // g\nvoid g(void)\n{\n}\nint d;\nvoid h(void)\n{\n}\n";
+        assert_eq!(
+            format(
+                program,
+                BlankLines {
+                    between_functions: 1,
+                    after_declarations: true,
+                    ..BlankLines::default()
+                }
+            ),
+            "void f(void)\n{\n    int a;\n    int b;\n\n    g();\n    int c;\n\n    h();\n}\n\n\
+             // g\nvoid g(void)\n{\n}\nint d;\nvoid h(void)\n{\n}\n"
+        );
+    }
+}
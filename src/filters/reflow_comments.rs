@@ -0,0 +1,526 @@
+use std::ops::Range;
+
+use crate::{doc::Rendered, doxygen, Config, Location, Token, TokenType, LINE_ENDING};
+
+use super::lines;
+
+/// the new text of comments on lines of their own
+enum Text {
+    /// the text of a run of `//` comments, one per line, without the `//`
+    Lines(Vec<String>),
+    /// the text of a block comment, without the `/*` and `*/`
+    Block(String),
+}
+
+struct Reflowed {
+    /// the lines of the rendered tokens the comment replaces
+    lines: Range<usize>,
+    col: usize,
+    text: Text,
+}
+
+/// a group of lines of a comment that are wrapped together
+struct Paragraph {
+    lines: Range<usize>,
+    /// the indentation of the lines after the first, like after the `- ` of a list item
+    hang: usize,
+    verbatim: bool,
+}
+
+/// the comments on lines of their own that go past the column limit, wrapped to fit, and the
+/// doc comments, with their framing and commands laid out
+///
+/// the text is made before the rendered tokens borrow it with [`Reflow::apply`]
+pub(crate) struct Reflow {
+    comments: Vec<Reflowed>,
+}
+
+/// the comment that is alone on `line`
+fn own_line<'a>(tokens: &[Token<'a>], line: &Range<usize>) -> Option<Token<'a>> {
+    let [comment, end] = &tokens[line.clone()] else {
+        return None;
+    };
+    let is_comment = matches!(
+        comment.token_type,
+        TokenType::Comment(_) | TokenType::BlockComment(_)
+    );
+    (is_comment && end.token_type == TokenType::Linebreak).then_some(*comment)
+}
+
+/// splits the text of a `//` comment into its marker, like the `/` of `///`, and its content
+fn line_comment(text: &str) -> Option<(&str, &str)> {
+    let marker = text.len() - text.trim_start_matches(['/', '!']).len();
+    let (marker, rest) = text.split_at(marker);
+    match rest {
+        "" => Some((marker, "")),
+        rest => Some((marker, rest.strip_prefix(' ')?.trim_end())),
+    }
+}
+
+/// the number of the item a line starts with, like `12.` or `3)`
+fn numbered(line: &str) -> Option<usize> {
+    let digits = line.len()
+        - line
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    let rest = &line[digits..];
+    (digits > 0 && (rest.starts_with(". ") || rest.starts_with(") "))).then_some(digits + 2)
+}
+
+/// the width of the marker of a list item, like `- `, that lines continuing it are indented by
+fn bullet(line: &str) -> Option<usize> {
+    match ["- ", "* ", "+ "]
+        .iter()
+        .any(|bullet| line.starts_with(bullet))
+    {
+        true => Some(2),
+        false => numbered(line),
+    }
+}
+
+/// whether a line looks like part of a drawing or a table rather than text
+fn is_diagram(line: &str) -> bool {
+    let symbols = line
+        .chars()
+        .filter(|ch| !ch.is_alphanumeric() && !ch.is_whitespace())
+        .count();
+    let letters = line.chars().filter(|ch| ch.is_alphanumeric()).count();
+    ["   ", "---", "===", "___", "|", "+-"]
+        .iter()
+        .any(|pattern| line.contains(pattern))
+        || symbols > letters
+}
+
+/// splits the lines of a comment into paragraphs, lines that are kept as they are form
+/// paragraphs of their own
+fn paragraphs(content: &[&str]) -> Vec<Paragraph> {
+    let mut paragraphs: Vec<Paragraph> = vec![];
+    let mut fenced = false;
+    for (idx, line) in content.iter().enumerate() {
+        let last = paragraphs.last_mut().filter(|last| !last.verbatim);
+        let verbatim = if line.trim_start().starts_with("```") || doxygen::is_code_fence(line) {
+            fenced = !fenced;
+            true
+        } else if fenced || line.is_empty() {
+            true
+        } else if let Some(last) = last {
+            let continued = indentation(line) == last.hang;
+            let text = line.trim_start();
+            let starts = bullet(text).is_some() || doxygen::is_command(text);
+            if continued && !starts && !is_diagram(text) {
+                last.lines.end = idx + 1;
+                continue;
+            }
+            line.starts_with(' ') || is_diagram(text)
+        } else {
+            line.starts_with(' ') || is_diagram(line)
+        };
+        paragraphs.push(Paragraph {
+            lines: idx..idx + 1,
+            hang: match verbatim {
+                true => 0,
+                false => bullet(line).unwrap_or_default(),
+            },
+            verbatim,
+        });
+    }
+    paragraphs
+}
+
+/// wraps the paragraphs of `content` with lines that are too long, the first line has room for
+/// `first` columns, the others for `rest` and the last one for `reserved` columns less
+///
+/// returns `None` if every line fits
+fn wrap(content: &[&str], first: usize, rest: usize, reserved: usize) -> Option<Vec<String>> {
+    let room = |idx: usize| match idx {
+        0 => first,
+        _ => rest,
+    };
+    let paragraphs = paragraphs(content);
+    let too_long = |paragraph: &Paragraph| {
+        !paragraph.verbatim
+            && paragraph.lines.clone().any(|idx| {
+                let reserved = if idx + 1 == content.len() {
+                    reserved
+                } else {
+                    0
+                };
+                content[idx].chars().count() + reserved > room(idx)
+            })
+    };
+    if !paragraphs.iter().any(too_long) {
+        return None;
+    }
+
+    let mut out = vec![];
+    for paragraph in &paragraphs {
+        if !too_long(paragraph) {
+            out.extend(
+                content[paragraph.lines.clone()]
+                    .iter()
+                    .map(|line| line.to_string()),
+            );
+            continue;
+        }
+        let indent = " ".repeat(paragraph.hang);
+        let mut line = String::new();
+        let words = content[paragraph.lines.clone()]
+            .iter()
+            .flat_map(|line| line.split_whitespace());
+        for word in words {
+            let empty = line.trim_start().is_empty();
+            if empty {
+                line.push_str(word);
+            } else if line.chars().count() + 1 + word.chars().count() > room(out.len()) {
+                out.push(std::mem::replace(&mut line, format!("{indent}{word}")));
+            } else {
+                line.push(' ');
+                line.push_str(word);
+            }
+        }
+        out.push(line);
+    }
+    Some(out)
+}
+
+/// wraps the content of a run of `//` comments starting at `col` with the same marker
+fn reflow_lines(texts: &[&str], col: usize, config: &Config) -> Option<Vec<String>> {
+    let (marker, _) = line_comment(texts[0])?;
+    let content: Vec<_> = texts
+        .iter()
+        .map(|text| line_comment(text).map_or("", |(_, content)| content))
+        .collect();
+    let room = config.column_limit.saturating_sub(col + 3 + marker.len());
+    let wrapped = wrap(&content, room, room, 0)?;
+    let texts = wrapped.iter().map(|line| match line.as_str() {
+        "" => marker.to_string(),
+        line => format!("{marker} {line}"),
+    });
+    Some(texts.collect())
+}
+
+/// the number of bytes of whitespace `line` starts with
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// the lines of a block comment
+struct Block<'t> {
+    /// the `*` of `/**` or the `!` of `/*!`
+    marker: &'t str,
+    /// whether the first line has content after the `/*`
+    first: bool,
+    /// the text of each line without what they start with
+    content: Vec<&'t str>,
+    /// what the lines after the first start with, like ` * `
+    prefix: String,
+    star: bool,
+    /// the whitespace before the `*/`, if it is on a line of its own
+    closing: Option<&'t str>,
+}
+
+impl<'t> Block<'t> {
+    /// splits the text of a block comment starting at `col` into its lines, or returns `None`
+    /// if their prefixes are mixed
+    fn parse(text: &'t str, col: usize) -> Option<Self> {
+        let mut lines: Vec<&str> = text.split(LINE_ENDING).collect();
+        let first = lines.remove(0);
+        let marker = first.len() - first.trim_start_matches(['*', '!']).len();
+        let (marker, first) = first.split_at(marker);
+        let first = match first.trim_end() {
+            "" => None,
+            first => Some(first.strip_prefix(' ')?),
+        };
+        let closing = match lines.last() {
+            Some(last) if last.trim().is_empty() => lines.pop(),
+            _ => None,
+        };
+
+        let filled = || lines.iter().filter(|line| !line.trim().is_empty());
+        let starred = filled().filter(|line| line.trim_start().starts_with('*'));
+        let prefix = match (starred.count(), filled().count()) {
+            (0, 0) => format!("{} * ", " ".repeat(col)),
+            (0, _) => {
+                let line = filled().min_by_key(|line| indentation(line))?;
+                line[..indentation(line)].to_string()
+            }
+            (starred, count) if starred == count => {
+                let line = filled().next()?;
+                format!("{}* ", &line[..indentation(line)])
+            }
+            _ => return None,
+        };
+        let star = prefix.ends_with("* ");
+        let mut content: Vec<&str> = first.into_iter().collect();
+        for line in &lines {
+            let line = line.trim_end();
+            let line = match star {
+                true => match line.trim_start().strip_prefix('*')? {
+                    "" => "",
+                    line => line.strip_prefix(' ')?,
+                },
+                false => line.get(prefix.len()..).unwrap_or_default(),
+            };
+            content.push(line);
+        }
+        Some(Self {
+            marker,
+            first: first.is_some(),
+            content,
+            prefix,
+            star,
+            closing,
+        })
+    }
+}
+
+/// wraps the content of a block comment starting at `col`, keeping the ` * ` or the
+/// indentation its lines start with and whether the `*/` is on a line of its own
+fn reflow_block(block: Block, col: usize, config: &Config) -> Option<String> {
+    let Block {
+        marker,
+        first,
+        content,
+        prefix,
+        star,
+        closing,
+    } = block;
+    let opening = col + 2 + marker.len() + 1;
+    let room = config.column_limit.saturating_sub(prefix.chars().count());
+    let first_room = match first {
+        true => config.column_limit.saturating_sub(opening),
+        false => room,
+    };
+    let reserved = match closing {
+        Some(_) => 0,
+        None => 3,
+    };
+    let wrapped = wrap(&content, first_room, room, reserved)?;
+
+    let mut text = marker.to_string();
+    for (idx, line) in wrapped.iter().enumerate() {
+        match (idx, first) {
+            (0, true) => text.push(' '),
+            _ => {
+                text.push_str(LINE_ENDING);
+                text.push_str(&prefix);
+            }
+        }
+        text.push_str(line);
+        // a line without content does not keep the space after the `*`
+        text.truncate(text.trim_end_matches(' ').len());
+    }
+    let last = wrapped.last().map_or(0, |line| line.chars().count());
+    let last_room = match wrapped.len() {
+        1 if first => first_room,
+        _ => room,
+    };
+    match closing {
+        Some(closing) => {
+            text.push_str(LINE_ENDING);
+            text.push_str(closing);
+        }
+        None if last + 3 > last_room => {
+            text.push_str(LINE_ENDING);
+            match star {
+                true => text.push_str(&prefix[..prefix.len() - 2]),
+                false => text.push_str(&" ".repeat(col)),
+            }
+        }
+        None => text.push(' '),
+    }
+    Some(text)
+}
+
+/// puts the `/**` and `*/` of a doc comment starting at `col` on lines of their own with ` * `
+/// before each line between them, and lines up the descriptions of its commands
+///
+/// a comment on one line is kept if it has no commands and fits
+fn doc_block(block: Block, text: &str, col: usize, config: &Config) -> Option<String> {
+    let room = config.column_limit.saturating_sub(col + 3);
+    let one_line = !text.contains(LINE_ENDING)
+        && col + text.chars().count() + 4 <= config.column_limit
+        && !block.content.iter().any(|line| doxygen::is_command(line));
+    if one_line {
+        return None;
+    }
+    let start = block.content.iter().position(|line| !line.is_empty());
+    let end = block.content.iter().rposition(|line| !line.is_empty());
+    let content = match (start, end) {
+        (Some(start), Some(end)) => &block.content[start..=end],
+        _ => &[],
+    };
+    let mut lines = doxygen::normalize(content, room);
+    if config.comments.reflow {
+        let content: Vec<_> = lines.iter().map(String::as_str).collect();
+        if let Some(wrapped) = wrap(&content, room, room, 0) {
+            lines = wrapped;
+        }
+    }
+
+    let prefix = format!("{} * ", " ".repeat(col));
+    let mut framed = block.marker.to_string();
+    for line in &lines {
+        framed.push_str(LINE_ENDING);
+        framed.push_str(&prefix);
+        framed.push_str(line);
+        framed.truncate(framed.trim_end_matches(' ').len());
+    }
+    framed.push_str(LINE_ENDING);
+    framed.push_str(&" ".repeat(col + 1));
+    (framed != text).then_some(framed)
+}
+
+impl Reflow {
+    pub(crate) fn new(tokens: &[Token], config: &Config) -> Self {
+        let lines = lines(tokens);
+        let mut comments = vec![];
+        let mut idx = 0;
+        while idx < lines.len() {
+            let Some(comment) = own_line(tokens, &lines[idx]) else {
+                idx += 1;
+                continue;
+            };
+            let col = comment.location.col;
+            let (end, text) = match comment.token_type {
+                TokenType::Comment(text) if config.comments.reflow => {
+                    let marker = line_comment(text).map(|(marker, _)| marker);
+                    let mut texts = vec![text];
+                    let mut end = idx + 1;
+                    while let Some(next) = lines.get(end).and_then(|line| own_line(tokens, line)) {
+                        let TokenType::Comment(text) = next.token_type else {
+                            break;
+                        };
+                        let follows = next.location.col == col
+                            && next.location.line == comment.location.line + texts.len();
+                        let same_marker = line_comment(text).map(|(marker, _)| marker) == marker;
+                        if marker.is_none() || !follows || !same_marker {
+                            break;
+                        }
+                        texts.push(text);
+                        end += 1;
+                    }
+                    (end, reflow_lines(&texts, col, config).map(Text::Lines))
+                }
+                TokenType::BlockComment(text) => {
+                    let block = Block::parse(text, col);
+                    let text = block.and_then(|block| match block.marker {
+                        "*" | "!" if config.comments.doxygen => doc_block(block, text, col, config),
+                        _ if config.comments.reflow => reflow_block(block, col, config),
+                        _ => None,
+                    });
+                    (idx + 1, text.map(Text::Block))
+                }
+                _ => (idx + 1, None),
+            };
+            if let Some(text) = text {
+                comments.push(Reflowed {
+                    lines: idx..end,
+                    col,
+                    text,
+                });
+            }
+            idx = end;
+        }
+        Self { comments }
+    }
+
+    /// replaces the comments of `rendered` with their wrapped text
+    pub(crate) fn apply<'a>(&'a self, rendered: &mut Rendered<'a>) {
+        if self.comments.is_empty() {
+            return;
+        }
+        let lines = lines(&rendered.tokens);
+        let mut comments = self.comments.iter().peekable();
+        let mut tokens = vec![];
+        let mut levels = vec![];
+        let mut row = 0;
+        let mut idx = 0;
+        while idx < lines.len() {
+            let line = &lines[idx];
+            let old = &rendered.tokens[line.start];
+            let level = rendered.levels.get(old.location.line).copied();
+            let level = level.unwrap_or_default();
+            let Some(comment) = comments.next_if(|comment| comment.lines.start == idx) else {
+                let first_row = old.location.line;
+                for token in &rendered.tokens[line.clone()] {
+                    let mut token = *token;
+                    token.location.line = token.location.line - first_row + row;
+                    tokens.push(token);
+                }
+                let last_row = rendered.tokens[line.end - 1].location.line;
+                for old in first_row..=last_row {
+                    levels.push(rendered.levels.get(old).copied().unwrap_or_default());
+                }
+                row += last_row - first_row + 1;
+                idx += 1;
+                continue;
+            };
+
+            let mut push = |token_type: TokenType<'a>| {
+                let location = Location::new(row, comment.col);
+                tokens.push(Token::with_offset(token_type, location, old.offset));
+                let text = token_type.to_str();
+                let end = match text.rsplit_once(LINE_ENDING) {
+                    Some((_, tail)) => tail.chars().count(),
+                    None => comment.col + token_type.width(),
+                };
+                row += token_type.height() - 1;
+                let location = Location::new(row, end);
+                tokens.push(Token::new(TokenType::Linebreak, location));
+                levels.extend(std::iter::repeat_n(level, token_type.height()));
+                row += 1;
+            };
+            match &comment.text {
+                Text::Lines(texts) => {
+                    for text in texts {
+                        push(TokenType::Comment(text));
+                    }
+                }
+                Text::Block(text) => push(TokenType::BlockComment(text)),
+            }
+            idx = comment.lines.end;
+        }
+        rendered.tokens = tokens;
+        rendered.levels = levels;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config};
+
+    fn format(program: &str, column_limit: usize) -> String {
+        let config = Config {
+            column_limit,
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn line_comments() {
+        let program = "// one two three four five six\n// seven\n//\n// - eight nine ten eleven\n//\n//     keep(this, code, as, it, is);\n// +------+  +------+\nint x;\n";
+        assert_eq!(
+            format(program, 24),
+            "// one two three four\n// five six seven\n//\n// - eight nine ten\n//   eleven\n//\n//     keep(this, code, as, it, is);\n// +------+  +------+\nint x;\n"
+        );
+    }
+
+    #[test]
+    fn block_comments() {
+        assert_eq!(
+            format("/* one two three four five six */\nint x;\n", 24),
+            "/* one two three four\n * five six */\nint x;\n"
+        );
+        assert_eq!(
+            format(
+                "/*\n * one two three four five six\n *\n * seven\n */\nint x;\n",
+                24
+            ),
+            "/*\n * one two three four\n * five six\n *\n * seven\n */\nint x;\n"
+        );
+        let program = "void f(void)\n{\n    x(); // one two three four five six\n}\n";
+        assert_eq!(format(program, 24), program);
+    }
+}
@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use crate::{
+    ast::DirectiveKind, doc::Rendered, parser, regex::Regex, Includes, Location, PunctType, Token,
+    TokenType, Warning,
+};
+
+use super::{lines, rearrange};
+
+/// an `#include` on a line of its own
+struct Include<'a> {
+    line: Range<usize>,
+    /// the header, with its quotes or angle brackets
+    header: &'a str,
+    category: usize,
+}
+
+/// the header included by the directive on `line`, if it is an include on a single row
+fn header<'a>(tokens: &[Token<'a>], line: &Range<usize>) -> Option<&'a str> {
+    let hash = &tokens[line.start];
+    let single_row = tokens[line.end - 1].location.line == hash.location.line;
+    if hash.token_type != TokenType::Punctuation(PunctType::Hash)
+        || !single_row
+        || parser::directive_kind(tokens, line.start) != DirectiveKind::Include
+    {
+        return None;
+    }
+    let TokenType::Directive(text) = tokens.get(line.start + 1)?.token_type else {
+        return None;
+    };
+    let header = text.trim_start_matches(|ch: char| ch.is_alphanumeric() || ch == '_');
+    Some(header.trim())
+}
+
+/// sorts the includes of each block of lines of includes, by their categories and then by the
+/// names of their headers
+///
+/// the lines keep the comments at their ends. a category that is not a valid pattern matches no
+/// header, with a warning at the start of the program
+pub(crate) fn sort_includes(rendered: &mut Rendered, includes: &Includes) -> Vec<Warning> {
+    let patterns: Vec<_> = includes
+        .categories
+        .iter()
+        .map(|pattern| Regex::new(pattern))
+        .collect();
+    let warnings = includes
+        .categories
+        .iter()
+        .zip(&patterns)
+        .filter(|(_, pattern)| pattern.is_none())
+        .map(|(category, _)| Warning {
+            location: Location::new(0, 0),
+            message: format!("the include category `{category}` is not a valid pattern"),
+        })
+        .collect();
+    let category = |header: &str| {
+        let name = header.trim_matches(['<', '>', '"']);
+        let main = includes
+            .main_header
+            .as_deref()
+            .is_some_and(|main| name == main || name.ends_with(&format!("/{main}")));
+        if main {
+            return 0;
+        }
+        let category = patterns.iter().position(|pattern| {
+            pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(header))
+        });
+        category.unwrap_or(patterns.len()) + 1
+    };
+
+    let tokens = &rendered.tokens;
+    let all = lines(tokens);
+    let mut arranged = vec![];
+    let mut i = 0;
+    while i < all.len() {
+        let mut block = vec![];
+        while let Some(header) = all.get(i).and_then(|line| header(tokens, line)) {
+            block.push(Include {
+                line: all[i].clone(),
+                header,
+                category: category(header),
+            });
+            i += 1;
+        }
+        if block.is_empty() {
+            arranged.push(Some(all[i].clone()));
+            i += 1;
+            continue;
+        }
+        block.sort_by(|a, b| (a.category, a.header).cmp(&(b.category, b.header)));
+        for (j, include) in block.iter().enumerate() {
+            let new_category = j > 0 && block[j - 1].category != include.category;
+            if includes.separate_categories && new_category {
+                arranged.push(None);
+            }
+            arranged.push(Some(include.line.clone()));
+        }
+    }
+
+    let unchanged = arranged.len() == all.len()
+        && arranged
+            .iter()
+            .zip(&all)
+            .all(|(arranged, line)| arranged.as_ref() == Some(line));
+    if !unchanged {
+        rearrange(rendered, &arranged);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config, Includes};
+
+    fn format(program: &str, includes: Includes) -> String {
+        let config = Config {
+            includes,
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn includes_are_sorted() {
+        let program = "#include \"util.h\"\n#include <zlib.h> // compression\n#include <stdio.h>\n\
+                       #include \"list.h\"\n#include <curl/curl.h>\n#include <sys/types.h>\n\n\
+                       #include <b.h>\n#include <a.h>\n#ifdef X\n#include <d.h>\n#endif\n#include <c.h>\n";
+        assert_eq!(
+            format(
+                program,
+                Includes {
+                    main_header: Some("list.h".to_string()),
+                    ..Includes::default()
+                }
+            ),
+            "#include \"list.h\"\n#include <stdio.h>\n#include <sys/types.h>\n\
+             #include <zlib.h> // compression\n#include <curl/curl.h>\n#include \"util.h\"\n\n\
+             #include <a.h>\n#include <b.h>\n#ifdef X\n#include <d.h>\n#endif\n#include <c.h>\n"
+        );
+        assert_eq!(
+            format(
+                program,
+                Includes {
+                    categories: vec!["^<".to_string()],
+                    separate_categories: true,
+                    ..Includes::default()
+                }
+            ),
+            "#include <curl/curl.h>\n#include <stdio.h>\n#include <sys/types.h>\n\
+             #include <zlib.h> // compression\n\n#include \"list.h\"\n#include \"util.h\"\n\n\
+             #include <a.h>\n#include <b.h>\n#ifdef X\n#include <d.h>\n#endif\n#include <c.h>\n"
+        );
+        assert_eq!(
+            format(
+                program,
+                Includes {
+                    sort: false,
+                    ..Includes::default()
+                }
+            ),
+            program
+        );
+    }
+
+    #[test]
+    fn invalid_categories() {
+        let program = "#include <b.h>\n#include <a.h>\n";
+        let config = Config {
+            includes: Includes {
+                categories: vec!["(".to_string(), "^<".to_string()],
+                ..Includes::default()
+            },
+            ..Config::default()
+        };
+        let formatted = format_with_config(program, &config);
+        assert_eq!(formatted.output, "#include <a.h>\n#include <b.h>\n");
+        assert_eq!(formatted.warnings.len(), 1);
+        assert_eq!(
+            formatted.warnings[0].message,
+            "the include category `(` is not a valid pattern"
+        );
+    }
+}
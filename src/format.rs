@@ -0,0 +1,849 @@
+use crate::{
+    ast::{Directive, DirectiveKind, Item, TokenIdx, TranslationUnit, Verbatim},
+    doc::Doc,
+    parser, Config, DirectiveIndent, ParenType, PunctType, Token, TokenType, LINE_ENDING,
+};
+
+mod decl;
+mod expr;
+mod spacing;
+mod stmt;
+
+use spacing::Space;
+
+/// a name declared along with its type, by the source offsets of its tokens
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DeclaredName {
+    /// the first token of the type
+    pub(crate) ty: usize,
+    /// the first token of the declarator that is not part of the type, like a `*`
+    pub(crate) start: usize,
+    /// the name, or the `:` of a bitfield without one
+    pub(crate) name: usize,
+}
+
+/// the tokens that are lined up after the layout is rendered, by their source offsets
+#[derive(Debug, Default)]
+pub(crate) struct Marks {
+    pub(crate) names: Vec<DeclaredName>,
+    /// the `:` before the width of each bitfield
+    pub(crate) bitfield_colons: Vec<usize>,
+    /// the closing braces of function definitions that are followed by another one
+    pub(crate) function_ends: Vec<usize>,
+    /// the `;` of the last declaration before a statement in a block
+    pub(crate) declaration_ends: Vec<usize>,
+}
+
+/// builds the layout of a parsed program, along with the tokens to line up after rendering it
+///
+/// the tree refers to its tokens by index, the comments and line breaks between them are read
+/// from the tokens as the tree is walked. comments keep their place relative to the code, and
+/// empty lines between declarations and statements are kept, at most one at a time.
+pub(crate) fn format<'a>(
+    source: &'a str,
+    tokens: &[Token<'a>],
+    unit: &TranslationUnit,
+    config: &Config,
+) -> (Doc<'a>, Marks) {
+    let mut formatter = Formatter {
+        source,
+        tokens,
+        config,
+        next: 0,
+        marks: Marks::default(),
+        conditionals: 0,
+        include_guard: match config.directives.skip_include_guard {
+            true => parser::include_guard(tokens),
+            false => None,
+        },
+    };
+    let doc = formatter.translation_unit(unit);
+    (doc, formatter.marks)
+}
+
+pub(crate) struct Formatter<'t, 'a> {
+    source: &'a str,
+    tokens: &'t [Token<'a>],
+    config: &'t Config,
+    /// the first token that has not been written yet
+    next: TokenIdx,
+    marks: Marks,
+    /// the number of conditionals open at the next directive
+    conditionals: usize,
+    /// the `#` of the `#ifndef` and `#endif` of an include guard, which are not counted
+    include_guard: Option<(TokenIdx, TokenIdx)>,
+}
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    fn translation_unit(&mut self, unit: &TranslationUnit) -> Doc<'a> {
+        let mut docs = vec![];
+        for (i, item) in unit.items.iter().enumerate() {
+            if let (Item::Function(function), Some(Item::Function(_))) =
+                (item, unit.items.get(i + 1))
+            {
+                let close = self.tokens[function.body.close].offset;
+                self.marks.function_ends.push(close);
+            }
+            let separator = self.separator();
+            let item = self.item(item);
+            docs.push(Doc::Concat(vec![separator, item]));
+        }
+        docs.push(self.trivia_before(self.tokens.len()));
+        Doc::Concat(docs)
+    }
+
+    fn item(&mut self, item: &Item) -> Doc<'a> {
+        match item {
+            Item::Directive(directive) => self.directive(directive),
+            Item::Declaration(declaration) => self.declaration(declaration),
+            Item::Function(function) => self.function(function),
+            Item::StaticAssert(static_assert) => self.static_assert(static_assert),
+            Item::Macro(call) => self.macro_call(call),
+            Item::Empty(semi) => self.token(*semi),
+            Item::Verbatim(verbatim) => self.verbatim(verbatim),
+        }
+    }
+
+    /// the token at `idx` along with the comments around it
+    ///
+    /// tokens have to be written in the order they appear in the source
+    fn token(&mut self, idx: TokenIdx) -> Doc<'a> {
+        debug_assert!(idx >= self.next, "token {idx} is written out of order");
+        let leading = self.trivia_before(idx);
+        self.next = idx + 1;
+        let trailing = self.trailing_comments();
+        Doc::Concat(vec![leading, Doc::Token(self.tokens[idx]), trailing])
+    }
+
+    /// the line break between two declarations or statements, which is an empty line if there
+    /// is one in the source, followed by the comments before the next one
+    fn separator(&mut self) -> Doc<'a> {
+        let newlines = self.tokens[self.next..]
+            .iter()
+            .take_while(|token| token.token_type == TokenType::Linebreak)
+            .count();
+        let line_break = match newlines {
+            0 | 1 => Doc::HardLine,
+            _ => Doc::BlankLine,
+        };
+        Doc::Concat(vec![line_break, self.leading_comments()])
+    }
+
+    /// the line break before an entry of a braced body, where empty lines are dropped before the
+    /// first entry
+    fn line_separator(&mut self, first: bool) -> Doc<'a> {
+        match first {
+            true => Doc::Concat(vec![Doc::HardLine, self.leading_comments()]),
+            false => self.separator(),
+        }
+    }
+
+    /// like [`Formatter::line_separator`], with a line break that is a space if the entries fit
+    /// on one line
+    fn list_separator(&mut self, first: bool) -> Doc<'a> {
+        let newlines = self.tokens[self.next..]
+            .iter()
+            .take_while(|token| token.token_type == TokenType::Linebreak)
+            .count();
+        let line_break = match (first, newlines) {
+            (false, 2..) => Doc::BlankLine,
+            _ => Doc::Line,
+        };
+        Doc::Concat(vec![line_break, self.leading_comments()])
+    }
+
+    /// the comments before the next token that is not trivia, written at the indentation of the
+    /// construct it starts rather than of the innermost one
+    fn leading_comments(&mut self) -> Doc<'a> {
+        let mut end = self.next;
+        while self
+            .token_type(end)
+            .is_some_and(|token_type| token_type.is_trivia())
+        {
+            end += 1;
+        }
+        self.trivia_before(end)
+    }
+
+    /// the comments on the rest of the line of the last token written, unless code follows them
+    fn trailing_comments(&mut self) -> Doc<'a> {
+        // the comments after an opening parenthesis go before the first entry, so that the line
+        // break after them leaves the entries lined up
+        let last = self
+            .next
+            .checked_sub(1)
+            .and_then(|last| self.token_type(last));
+        if let Some(TokenType::Paren(ParenType::LParen | ParenType::LBrack)) = last {
+            return Doc::Nil;
+        }
+        let mut end = self.next;
+        while let Some(TokenType::BlockComment(_)) = self.token_type(end) {
+            end += 1;
+        }
+        let line_comment = matches!(self.token_type(end), Some(TokenType::Comment(_)));
+        if line_comment {
+            end += 1;
+        } else if !matches!(self.token_type(end), None | Some(TokenType::Linebreak)) {
+            return Doc::Nil;
+        }
+
+        let mut docs = vec![];
+        for idx in self.next..end {
+            docs.push(Doc::space());
+            docs.push(Doc::Token(self.tokens[idx]));
+        }
+        // the code after a comment ending the line stays on the next line
+        let line_ends = matches!(self.token_type(end), Some(TokenType::Linebreak));
+        if line_comment || (end > self.next && line_ends) {
+            docs.push(Doc::HardLine);
+        }
+        self.next = end;
+        Doc::Concat(docs)
+    }
+
+    /// the comments between the last token written and `idx`, along with any tokens the tree does
+    /// not refer to
+    fn trivia_before(&mut self, idx: TokenIdx) -> Doc<'a> {
+        let mut docs = vec![];
+        let mut newlines = 0;
+        // whether the last thing written is a comment on its own line
+        let mut own_line = false;
+        while self.next < idx {
+            let token = self.tokens[self.next];
+            match token.token_type {
+                TokenType::Linebreak => newlines += 1,
+                TokenType::LineContinuation => {}
+                TokenType::Comment(_) | TokenType::BlockComment(_) => {
+                    own_line = self.starts_line(self.next);
+                    docs.push(match (own_line, newlines) {
+                        (false, _) if self.space_before(self.next) => Doc::space(),
+                        (false, _) => Doc::Nil,
+                        (true, 0 | 1) => Doc::HardLine,
+                        (true, _) => Doc::BlankLine,
+                    });
+                    docs.push(Doc::Token(token));
+                    let line_ends = self.token_type(self.next + 1) == Some(TokenType::Linebreak);
+                    match token.token_type {
+                        TokenType::Comment(_) => docs.push(Doc::HardLine),
+                        _ if line_ends => docs.push(Doc::HardLine),
+                        _ if self.space_after(self.next) => docs.push(Doc::space()),
+                        _ => {}
+                    }
+                    newlines = 0;
+                }
+                TokenType::Punctuation(PunctType::Hash) => {
+                    let end = parser::directive_end(self.tokens, self.next);
+                    let directive = self.directive_lines(self.next, end);
+                    docs.push(directive);
+                    own_line = true;
+                    newlines = 0;
+                    continue;
+                }
+                _ => {
+                    docs.push(Doc::Token(token));
+                    docs.push(Doc::space());
+                    own_line = false;
+                    newlines = 0;
+                }
+            }
+            self.next += 1;
+        }
+        if own_line {
+            docs.push(match newlines {
+                0 => Doc::Nil,
+                1 => Doc::HardLine,
+                _ => Doc::BlankLine,
+            });
+        }
+        Doc::Concat(docs)
+    }
+
+    /// indents `doc` by a level of block indentation
+    fn indent(&self, doc: Doc<'a>) -> Doc<'a> {
+        Doc::indent(self.config.indentation.width, doc)
+    }
+
+    /// indents `doc` as the continuation of a line
+    fn continuation(&self, doc: Doc<'a>) -> Doc<'a> {
+        Doc::hang(self.config.indentation.continuation, doc)
+    }
+
+    fn token_type(&self, idx: TokenIdx) -> Option<TokenType<'a>> {
+        self.tokens.get(idx).map(|token| token.token_type)
+    }
+
+    /// whether there is whitespace before the token at `idx` in the source
+    fn space_before(&self, idx: TokenIdx) -> bool {
+        let token = self.tokens[idx];
+        idx.checked_sub(1).is_some_and(|prev| {
+            let prev = self.tokens[prev];
+            prev.offset + prev.token_type.len() < token.offset
+        })
+    }
+
+    /// whether the inline comment at `idx` is followed by whitespace in the source, and not by
+    /// a token that never has a space before it like `)` or `,`
+    fn space_after(&self, idx: TokenIdx) -> bool {
+        let token = self.tokens[idx];
+        self.tokens.get(idx + 1).is_some_and(|next| {
+            let closer = matches!(
+                next.token_type,
+                TokenType::Paren(ParenType::RParen | ParenType::RBrack)
+                    | TokenType::Punctuation(PunctType::Comma | PunctType::Semicolon)
+            );
+            !closer && token.offset + token.token_type.len() < next.offset
+        })
+    }
+
+    /// whether the token at `idx` is the first one on its line
+    fn starts_line(&self, idx: TokenIdx) -> bool {
+        idx == 0 || self.tokens[idx - 1].token_type == TokenType::Linebreak
+    }
+
+    /// the column following the token at `idx` in the source
+    fn end_col(&self, idx: TokenIdx) -> usize {
+        let token = self.tokens[idx];
+        match token.token_type.to_str().rsplit_once(LINE_ENDING) {
+            Some((_, tail)) => tail.chars().count(),
+            None => token.location.col + token.token_type.width(),
+        }
+    }
+
+    /// a directive on lines of its own, starting at column 0
+    ///
+    /// the tokens keep their distance from each other, and continuation lines keep their columns
+    fn directive(&mut self, directive: &Directive) -> Doc<'a> {
+        let leading = self.trivia_before(directive.start);
+        let lines = self.directive_lines(directive.start, directive.end);
+        Doc::Concat(vec![leading, lines])
+    }
+
+    fn directive_lines(&mut self, start: TokenIdx, end: TokenIdx) -> Doc<'a> {
+        let depth = self.directive_depth(start);
+        let options = &self.config.directives;
+        // the columns before the `#`, and the spaces after it if they are not kept
+        let (before, after) = match options.indent {
+            DirectiveIndent::None => (0, None),
+            DirectiveIndent::AfterHash => (0, Some(depth * options.width)),
+            DirectiveIndent::BeforeHash => (depth * options.width, Some(0)),
+        };
+        // the lines continuing an indented directive move along with its `#`
+        let shift = match options.indent {
+            DirectiveIndent::BeforeHash => {
+                before as isize - self.tokens[start].location.col as isize
+            }
+            _ => 0,
+        };
+        let mut docs = vec![Doc::HardLine, Doc::Token(self.tokens[start])];
+        let mut prev = Some(start);
+        for idx in start + 1..end {
+            let token = self.tokens[idx];
+            match (token.token_type, prev) {
+                (TokenType::Linebreak, _) => prev = None,
+                (_, Some(prev_idx)) => {
+                    let gap = match after {
+                        Some(gap) if idx == start + 1 => gap,
+                        _ => token.location.col.saturating_sub(self.end_col(prev_idx)),
+                    };
+                    docs.push(Doc::Spaces(gap));
+                    docs.push(Doc::Token(token));
+                    prev = Some(idx);
+                }
+                (_, None) => {
+                    docs.push(Doc::column(
+                        (token.location.col as isize + shift).max(0) as usize,
+                        Doc::Concat(vec![Doc::HardLine, Doc::Token(token)]),
+                    ));
+                    prev = Some(idx);
+                }
+            }
+        }
+        self.next = end;
+        Doc::Concat(vec![Doc::column(before, Doc::Concat(docs)), Doc::HardLine])
+    }
+
+    /// the number of conditionals around the directive starting at `start`, counting a
+    /// conditional for its own `#elif`, `#else` and `#endif` lines but not for its `#if`
+    fn directive_depth(&mut self, start: TokenIdx) -> usize {
+        let guard = self
+            .include_guard
+            .is_some_and(|(ifndef, endif)| start == ifndef || start == endif);
+        if guard {
+            return self.conditionals;
+        }
+        let open = self.conditionals;
+        let inner = open.saturating_sub(1);
+        let (depth, after) = match parser::directive_kind(self.tokens, start) {
+            DirectiveKind::If => (open, open + 1),
+            DirectiveKind::Elif | DirectiveKind::Else => (inner, open),
+            DirectiveKind::Endif => (inner, inner),
+            _ => (open, open),
+        };
+        self.conditionals = after;
+        depth
+    }
+
+    /// the source text of a region that could not be parsed, exactly as it is
+    ///
+    /// a region starting its line keeps its indentation and is put at the start of a line
+    fn verbatim(&mut self, verbatim: &Verbatim) -> Doc<'a> {
+        let leading = self.trivia_before(verbatim.start);
+        let first = self.tokens[verbatim.start];
+        let last = self.tokens[verbatim.end - 1];
+        let end = last.offset + last.token_type.len();
+
+        let region = if self.starts_line(verbatim.start) {
+            let line_start = self.source[..first.offset]
+                .rfind(LINE_ENDING)
+                .map_or(0, |idx| idx + LINE_ENDING.len());
+            let token = Token::with_offset(
+                TokenType::Verbatim(&self.source[line_start..end]),
+                first.location,
+                line_start,
+            );
+            Doc::column(0, Doc::Concat(vec![Doc::HardLine, Doc::Token(token)]))
+        } else {
+            Doc::Token(Token::with_offset(
+                TokenType::Verbatim(&self.source[first.offset..end]),
+                first.location,
+                first.offset,
+            ))
+        };
+        self.next = verbatim.end;
+        let trailing = self.trailing_comments();
+        let line_break = match self.token_type(self.next) {
+            None | Some(TokenType::Linebreak) => Doc::HardLine,
+            Some(_) => Doc::Nil,
+        };
+        Doc::Concat(vec![leading, region, trailing, line_break])
+    }
+
+    /// the tokens `start..=end`, for code that is kept as a list of tokens, spaced by
+    /// [`Formatter::space_between`]
+    fn raw(&mut self, start: TokenIdx, end: TokenIdx) -> Doc<'a> {
+        let mut docs = vec![];
+        let mut before: Option<TokenIdx> = None;
+        let mut prev: Option<TokenIdx> = None;
+        for idx in start..=end {
+            if self.tokens[idx].token_type.is_trivia() || idx < self.next {
+                continue;
+            }
+            if let Some(prev) = prev {
+                let before = before.map(|before| self.tokens[before].token_type);
+                let prev_token = self.tokens[prev];
+                let next = self.tokens[idx];
+                let space = match self.space_between(before, prev_token.token_type, next.token_type)
+                {
+                    Space::None => false,
+                    Space::One => true,
+                    Space::Source => prev_token.offset + prev_token.token_type.len() < next.offset,
+                };
+                if space {
+                    docs.push(Doc::space());
+                }
+            }
+            docs.push(self.token(idx));
+            before = prev;
+            prev = Some(idx);
+        }
+        Doc::Concat(docs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        format_with_config, Braces, CaseBlocks, Config, DirectiveIndent, Directives, Enums,
+        GotoLabels, Indentation, Labels, PointerAlignment, Spacing, Tabs, TrailingComma, Wrapping,
+    };
+
+    fn format(program: &str) -> String {
+        format_with_config(program, &Config::default()).output
+    }
+
+    #[test]
+    fn declarations_and_functions() {
+        assert_eq!(
+            format("static  int x=1,*y ;\nint main( void ){return x+*y;}"),
+            "static int x = 1, *y;\nint main(void)\n{\n    return x + *y;\n}\n"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        assert_eq!(
+            format("void f(int n){if(n>0)n--;else{n++;}while(n)g(n,-n);for(;;)break;}"),
+            "void f(int n)\n{\n    if (n > 0)\n        n--;\n    else {\n        n++;\n    }\n    \
+             while (n)\n        g(n, -n);\n    for (;;)\n        break;\n}\n"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_broken() {
+        assert_eq!(
+            format(
+                "int f(void) { return some_function(first_argument, second_argument, \
+                 third_argument, fourth); }"
+            ),
+            "int f(void)\n{\n    return some_function(first_argument,\n\
+             \x20                        second_argument,\n\
+             \x20                        third_argument,\n\
+             \x20                        fourth);\n}\n"
+        );
+    }
+
+    #[test]
+    fn loosely_binding_operators_are_broken_first() {
+        assert_eq!(
+            format(
+                "int f(void) { return alpha && beta || gamma && delta || epsilon && zeta || \
+                 eta && theta || iota && kappa || lambda && mu; }"
+            ),
+            "int f(void)\n{\n    return alpha && beta ||\n\
+             \x20       gamma && delta ||\n\
+             \x20       epsilon && zeta ||\n\
+             \x20       eta && theta ||\n\
+             \x20       iota && kappa ||\n\
+             \x20       lambda && mu;\n}\n"
+        );
+    }
+
+    #[test]
+    fn indentation() {
+        let program = "void f(void) { if (x) { value = some_function(first_argument, \
+                       second_argument) + other_value * factor; } }";
+        let config = |width, continuation, tabs| Config {
+            indentation: Indentation {
+                width,
+                continuation,
+                tab_width: 8,
+                tabs,
+            },
+            ..Config::default()
+        };
+        assert_eq!(
+            format_with_config(program, &config(2, 6, Tabs::Never)).output,
+            "void f(void)\n{\n  if (x) {\n    value =\n          some_function(first_argument, \
+             second_argument) + other_value * factor;\n  }\n}\n"
+        );
+        assert_eq!(
+            format_with_config(program, &config(8, 8, Tabs::Smart)).output,
+            "void f(void)\n{\n\tif (x) {\n\t\tvalue = some_function(first_argument,\n\
+             \t\t                      second_argument) + other_value * factor;\n\t}\n}\n"
+        );
+        assert_eq!(
+            format_with_config(program, &config(8, 8, Tabs::Always)).output,
+            "void f(void)\n{\n\tif (x) {\n\t\tvalue = some_function(first_argument,\n\
+             \t\t\t\t      second_argument) + other_value * factor;\n\t}\n}\n"
+        );
+    }
+
+    #[test]
+    fn brace_styles() {
+        let program = "struct s { int a; };\nint f(int x) { if (x) { g(); } else { h(); } }";
+        let format = |braces| {
+            let config = Config {
+                braces,
+                ..Config::default()
+            };
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(Braces::k_and_r()),
+            "struct s {\n    int a;\n};\nint f(int x)\n{\n    if (x) {\n        g();\n    \
+             } else {\n        h();\n    }\n}\n"
+        );
+        assert_eq!(
+            format(Braces::stroustrup()),
+            "struct s {\n    int a;\n};\nint f(int x)\n{\n    if (x) {\n        g();\n    \
+             }\n    else {\n        h();\n    }\n}\n"
+        );
+        assert_eq!(
+            format(Braces::allman()),
+            "struct s\n{\n    int a;\n};\nint f(int x)\n{\n    if (x)\n    {\n        g();\n    \
+             }\n    else\n    {\n        h();\n    }\n}\n"
+        );
+        assert_eq!(
+            format(Braces::gnu()),
+            "struct s\n{\n    int a;\n};\nint f(int x)\n{\n    if (x)\n      {\n        g();\n      \
+             }\n    else\n      {\n        h();\n      }\n}\n"
+        );
+        assert_eq!(
+            format(Braces::whitesmiths()),
+            "struct s\n    {\n    int a;\n    };\nint f(int x)\n    {\n    if (x)\n        {\n        \
+             g();\n        }\n    else\n        {\n        h();\n        }\n    }\n"
+        );
+    }
+
+    #[test]
+    fn spacing() {
+        let mut config = Config::default();
+        config.macros.statement.push("CHECK".to_string());
+        let program = "CHECK(a,b+c,-d,x->y . z,p++,*q,arr [ 1 ]);\n\
+                       void f(int x){if(x)g(x);while(x--)h();}";
+        assert_eq!(
+            format_with_config(program, &config).output,
+            "CHECK(a, b + c, -d, x->y.z, p++, *q, arr[1]);\nvoid f(int x)\n{\n    if (x)\n        \
+             g(x);\n    while (x--)\n        h();\n}\n"
+        );
+        config.spacing = Spacing {
+            before_call_parens: true,
+            before_control_parens: false,
+            ..Spacing::default()
+        };
+        assert_eq!(
+            format_with_config(program, &config).output,
+            "CHECK (a, b + c, -d, x->y.z, p++, *q, arr[1]);\nvoid f (int x)\n{\n    if(x)\n        \
+             g (x);\n    while(x--)\n        h ();\n}\n"
+        );
+    }
+
+    #[test]
+    fn pointer_alignment() {
+        let program =
+            "char **argv, *q;\n\nconst char *const s;\nint *f(int *a, void (*cb)(int *)) \
+                       { return (int *)a; }";
+        let format = |pointer_alignment| {
+            let config = Config {
+                pointer_alignment,
+                ..Config::default()
+            };
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(PointerAlignment::Right),
+            "char **argv, *q;\n\nconst char *const s;\nint *f(int *a, void (*cb)(int *))\n{\n    \
+             return (int *)a;\n}\n"
+        );
+        assert_eq!(
+            format(PointerAlignment::Left),
+            "char **argv, *q;\n\nconst char* const s;\nint* f(int* a, void (*cb)(int*))\n{\n    \
+             return (int*)a;\n}\n"
+        );
+        assert_eq!(
+            format(PointerAlignment::Middle),
+            "char **argv, *q;\n\nconst char * const s;\nint * f(int * a, void (*cb)(int *))\n{\n    \
+             return (int *)a;\n}\n"
+        );
+    }
+
+    #[test]
+    fn labels() {
+        let program = "int f(int x)\n{\n    switch (x) {\n    case 1: {\n        g();\n    }\n    \
+                       case 2:\n    again:\n        break;\n    }\nout:\n    return 1;\n}\n";
+        let format = |labels| {
+            let config = Config {
+                labels,
+                ..Config::default()
+            };
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(Labels::default()),
+            "int f(int x)\n{\n    switch (x) {\n    case 1:\n        {\n            g();\n        }\n    \
+             case 2:\n    again:\n        break;\n    }\nout:\n    return 1;\n}\n"
+        );
+        assert_eq!(
+            format(Labels {
+                indent_cases: true,
+                case_blocks: CaseBlocks::Attached,
+                goto_labels: GotoLabels::WithCode,
+                ..Labels::default()
+            }),
+            "int f(int x)\n{\n    switch (x) {\n        case 1: {\n            g();\n        }\n        \
+             case 2:\n            again:\n            break;\n    }\n    out:\n    return 1;\n}\n"
+        );
+        assert_eq!(
+            format(Labels {
+                indent_case_bodies: false,
+                case_blocks: CaseBlocks::NextLine,
+                goto_labels: GotoLabels::FlushLeft,
+                ..Labels::default()
+            }),
+            "int f(int x)\n{\n    switch (x) {\n    case 1:\n    {\n        g();\n    }\n    \
+             case 2:\nagain:\n    break;\n    }\nout:\n    return 1;\n}\n"
+        );
+    }
+
+    #[test]
+    fn comments_before_labels() {
+        let program = "void f(int x)\n{\n    switch (x) {\n    case 1:\n        a();\n        \
+                       /* fallthrough */\n    case 2:\n        b();\n    /* last */\n    \
+                       default:\n        break;\n    }\n}\n";
+        assert_eq!(
+            format_with_config(program, &Config::default()).output,
+            program
+        );
+        let config = Config {
+            labels: Labels {
+                indent_cases: true,
+                ..Labels::default()
+            },
+            ..Config::default()
+        };
+        assert_eq!(
+            format_with_config(program, &config).output,
+            "void f(int x)\n{\n    switch (x) {\n        case 1:\n            a();\n            \
+             /* fallthrough */\n        case 2:\n            b();\n        /* last */\n        \
+             default:\n            break;\n    }\n}\n"
+        );
+    }
+
+    #[test]
+    fn labels_without_braces() {
+        let program = "int f(int x)\n{\n    switch (x) case 1: case 2: return 0;\n    \
+                       if (x) y: x++;\n    return 1;\n}\n";
+        assert_eq!(
+            format_with_config(program, &Config::default()).output,
+            "int f(int x)\n{\n    switch (x)\n        case 1:\n        case 2:\n            \
+             return 0;\n    if (x)\n        y:\n        x++;\n    return 1;\n}\n"
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let program = "enum dir { UP, DOWN };\nenum color {\n    RED = 1, // red\n    \
+                       BLUE = 300 // blue\n};\nenum t { A, B, };\n";
+        let format = |one_line, trailing_comma| {
+            let config = Config {
+                enums: Enums {
+                    one_line,
+                    trailing_comma,
+                },
+                ..Config::default()
+            };
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(true, TrailingComma::Keep),
+            "enum dir { UP, DOWN };\nenum color {\n    RED  = 1,  // red\n    \
+             BLUE = 300 // blue\n};\nenum t {\n    A,\n    B,\n};\n"
+        );
+        assert_eq!(
+            format(true, TrailingComma::Add),
+            "enum dir {\n    UP,\n    DOWN,\n};\nenum color {\n    RED  = 1,   // red\n    \
+             BLUE = 300, // blue\n};\nenum t {\n    A,\n    B,\n};\n"
+        );
+        assert_eq!(
+            format(true, TrailingComma::Remove),
+            "enum dir { UP, DOWN };\nenum color {\n    RED  = 1,  // red\n    \
+             BLUE = 300 // blue\n};\nenum t { A, B };\n"
+        );
+        assert_eq!(
+            format(false, TrailingComma::Keep),
+            "enum dir {\n    UP,\n    DOWN\n};\nenum color {\n    RED  = 1,  // red\n    \
+             BLUE = 300 // blue\n};\nenum t {\n    A,\n    B,\n};\n"
+        );
+        let program = "enum { A = 1 /* x */  };\n";
+        let formatted = format_with_config(program, &Config::default()).output;
+        assert_eq!(formatted, "enum {\n    A = 1 /* x */\n};\n");
+        assert_eq!(
+            format_with_config(&formatted, &Config::default()).output,
+            formatted
+        );
+    }
+
+    #[test]
+    fn wrapping() {
+        let program = "void f(void) { CALL(first_argument, second_argument + 1, third_argument, \
+                       fourth_argument, f(x, y)); }";
+        let format = |bin_pack, align_after_paren| {
+            let mut config = Config {
+                arguments: Wrapping {
+                    bin_pack,
+                    align_after_paren,
+                },
+                ..Config::default()
+            };
+            config.macros.statement.push("CALL".to_string());
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(false, true),
+            "void f(void)\n{\n    CALL(first_argument,\n         second_argument + 1,\n         \
+             third_argument,\n         fourth_argument,\n         f(x, y));\n}\n"
+        );
+        assert_eq!(
+            format(true, true),
+            "void f(void)\n{\n    CALL(first_argument, second_argument + 1, third_argument, \
+             fourth_argument,\n         f(x, y));\n}\n"
+        );
+        assert_eq!(
+            format(false, false),
+            "void f(void)\n{\n    CALL(\n        first_argument,\n        second_argument + 1,\n        \
+             third_argument,\n        fourth_argument,\n        f(x, y)\n    );\n}\n"
+        );
+        assert_eq!(
+            format(true, false),
+            "void f(void)\n{\n    CALL(\n        first_argument, second_argument + 1, third_argument, \
+             fourth_argument,\n        f(x, y)\n    );\n}\n"
+        );
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_kept() {
+        let program = "// header\n\nint a; // trailing\n\n\n/* own line */\nint b;\n";
+        assert_eq!(
+            format(program),
+            "// header\n\nint a; // trailing\n\n/* own line */\nint b;\n"
+        );
+    }
+
+    #[test]
+    fn inline_block_comments() {
+        let program = "int f(int /* a */ );\nint g(int a /* a */ , int b);\nvoid h(void)\n{\n    \
+                       x = 1 /* one */ + 2;\n}\n";
+        assert_eq!(
+            format(program),
+            "int f(int /* a */);\nint g(int a /* a */, int b);\nvoid h(void)\n{\n    \
+             x = 1 /* one */ + 2;\n}\n"
+        );
+        let program =
+            "int f(/* deprecated */\n      PLArenaPool *arena,\n      PLArenaPool *pool);\n";
+        assert_eq!(format(program), program);
+        assert_eq!(
+            format("int f(/* deprecated */\n    PLArenaPool *arena, PLArenaPool *pool);\n"),
+            program
+        );
+    }
+
+    #[test]
+    fn directives_keep_their_lines() {
+        let program = "  #define  MAX(a, b) \\\n      ((a) > (b))\nint x;\n";
+        assert_eq!(
+            format(program),
+            "#define  MAX(a, b) \\\n      ((a) > (b))\nint x;\n"
+        );
+    }
+
+    #[test]
+    fn directive_indentation() {
+        let program = "#ifndef GUARD\n#define GUARD\n#if A\n#  include <a.h>\n#elif B\nint x;\n\
+                       #ifdef C\n#define D(x) \\\n    f(x);    \\\n    g(x)\n#endif\n#else\n\
+                       #endif\n#endif\n";
+        let format = |indent, skip_include_guard| {
+            let config = Config {
+                directives: Directives {
+                    indent,
+                    skip_include_guard,
+                    ..Directives::default()
+                },
+                ..Config::default()
+            };
+            format_with_config(program, &config).output
+        };
+        assert_eq!(
+            format(DirectiveIndent::AfterHash, true),
+            "#ifndef GUARD\n#define GUARD\n#if A\n#  include <a.h>\n#elif B\nint x;\n\
+             #  ifdef C\n#    define D(x) \\\n    f(x);        \\\n    g(x)\n#  endif\n\
+             #else\n#endif\n#endif\n"
+        );
+        assert_eq!(
+            format(DirectiveIndent::BeforeHash, false),
+            "#ifndef GUARD\n  #define GUARD\n  #if A\n    #include <a.h>\n  #elif B\nint x;\n\
+             \x20   #ifdef C\n      #define D(x) \\\n          f(x);    \\\n          g(x)\n\
+             \x20   #endif\n  #else\n  #endif\n#endif\n"
+        );
+        assert_eq!(format(DirectiveIndent::None, true), program);
+    }
+}
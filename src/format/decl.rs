@@ -0,0 +1,595 @@
+use crate::{
+    ast::{
+        Declaration, Declarator, DirectDeclarator, Enum, EnumEntry, Enumerator, FunctionDef, Group,
+        InitDeclarator, MacroCall, Member, Param, ParamList, Record, Specifier, StaticAssert,
+        TokenIdx, TypeName,
+    },
+    doc::Doc,
+    BitfieldSpacing, BracePlacement, ParenType, PointerAlignment, PunctType, Token, TokenType,
+    TrailingComma,
+};
+
+use super::{DeclaredName, Formatter};
+
+/// the first token of a specifier
+fn specifier_start(specifier: &Specifier) -> TokenIdx {
+    match specifier {
+        Specifier::Token(idx) | Specifier::Call(idx, _) => *idx,
+        Specifier::Attribute(attribute) => attribute.open,
+        Specifier::Record(Record { keyword, .. }) | Specifier::Enum(Enum { keyword, .. }) => {
+            *keyword
+        }
+    }
+}
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    pub(super) fn declaration(&mut self, declaration: &Declaration) -> Doc<'a> {
+        let specifiers = self.specifiers(&declaration.specifiers);
+        if declaration.declarators.is_empty() {
+            return Doc::Concat(vec![specifiers, self.token(declaration.semi)]);
+        }
+
+        let alignment = match declaration.declarators.len() {
+            1 => self.config.pointer_alignment,
+            _ => PointerAlignment::Right,
+        };
+        let first = &declaration.declarators[0];
+        match (&first.bitfield, declaration.specifiers.first()) {
+            // the width of an unnamed bitfield is lined up with the names around it
+            (Some((colon, _)), Some(ty)) if first.declarator.name().is_none() => {
+                let colon = self.tokens[*colon].offset;
+                self.marks.names.push(DeclaredName {
+                    ty: self.tokens[specifier_start(ty)].offset,
+                    start: colon,
+                    name: colon,
+                });
+            }
+            _ => self.declare(&declaration.specifiers, &first.declarator, alignment),
+        }
+        let mut declarators = vec![];
+        for (i, declarator) in declaration.declarators.iter().enumerate() {
+            if i > 0 {
+                declarators.push(self.token(declaration.commas[i - 1]));
+                declarators.push(Doc::Line);
+            }
+            let alignment = match i {
+                0 if !declaration.specifiers.is_empty() => Some(alignment),
+                _ => None,
+            };
+            declarators.push(self.init_declarator(declarator, alignment));
+        }
+        let declarators = match declaration.declarators.len() {
+            1 => Doc::Concat(declarators),
+            _ => self.continuation(Doc::Concat(declarators)),
+        };
+        Doc::Concat(vec![
+            Doc::group(
+                self.config.penalties.list,
+                Doc::Concat(vec![specifiers, declarators]),
+            ),
+            self.token(declaration.semi),
+        ])
+    }
+
+    /// a declarator with its initializer, following the specifiers of the declaration if there
+    /// is an `alignment` for its pointers
+    fn init_declarator(
+        &mut self,
+        init_declarator: &InitDeclarator,
+        alignment: Option<PointerAlignment>,
+    ) -> Doc<'a> {
+        let declarator = &init_declarator.declarator;
+        let mut docs = vec![match alignment {
+            Some(alignment) => self.declarator_after_specifiers(declarator, alignment),
+            None => self.declarator(declarator),
+        }];
+        let (before, after): (Vec<_>, Vec<_>) =
+            init_declarator.attributes.iter().partition(|attribute| {
+                init_declarator
+                    .bitfield
+                    .as_ref()
+                    .is_none_or(|(colon, _)| specifier_start(attribute) < *colon)
+            });
+        for attribute in before {
+            docs.push(Doc::space());
+            docs.push(self.specifier(attribute));
+        }
+        if let Some((colon, width)) = &init_declarator.bitfield {
+            let (before, after) = match self.config.spacing.bitfield_colons {
+                BitfieldSpacing::Both => (true, true),
+                BitfieldSpacing::Before => (true, false),
+                BitfieldSpacing::After => (false, true),
+                BitfieldSpacing::None => (false, false),
+            };
+            if before {
+                docs.push(Doc::space());
+            }
+            self.marks.bitfield_colons.push(self.tokens[*colon].offset);
+            docs.push(self.token(*colon));
+            if after {
+                docs.push(Doc::space());
+            }
+            docs.push(self.expr(width));
+        }
+        for attribute in after {
+            docs.push(Doc::space());
+            docs.push(self.specifier(attribute));
+        }
+        if let Some((eq, init)) = &init_declarator.init {
+            docs.push(Doc::space());
+            docs.push(self.token(*eq));
+            docs.push(self.assigned(init));
+        }
+        Doc::Concat(docs)
+    }
+
+    pub(super) fn specifiers(&mut self, specifiers: &[Specifier]) -> Doc<'a> {
+        let docs: Vec<_> = specifiers
+            .iter()
+            .map(|specifier| self.specifier(specifier))
+            .collect();
+        Doc::join(docs, Doc::space())
+    }
+
+    fn specifier(&mut self, specifier: &Specifier) -> Doc<'a> {
+        match specifier {
+            Specifier::Token(idx) => self.token(*idx),
+            Specifier::Call(keyword, group) => {
+                Doc::Concat(vec![self.token(*keyword), self.group(group)])
+            }
+            Specifier::Attribute(attribute) => self.raw(attribute.open, attribute.close),
+            Specifier::Record(record) => self.record(record),
+            Specifier::Enum(enumeration) => self.enumeration(enumeration),
+        }
+    }
+
+    pub(super) fn group(&mut self, group: &Group) -> Doc<'a> {
+        self.raw(group.open, group.close)
+    }
+
+    /// the arguments of a macro invocation, which are kept as lists of tokens and wrapped like
+    /// the arguments of a call
+    pub(super) fn macro_args(&mut self, args: &Group) -> Doc<'a> {
+        let open = self.token(args.open);
+        let mut entries = vec![];
+        let mut start = args.open + 1;
+        let mut depth = 0;
+        for idx in args.open + 1..args.close {
+            match self.tokens[idx].token_type {
+                TokenType::Paren(ParenType::LParen | ParenType::LBrack | ParenType::LBrace) => {
+                    depth += 1
+                }
+                TokenType::Paren(ParenType::RParen | ParenType::RBrack | ParenType::RBrace) => {
+                    depth -= 1
+                }
+                TokenType::Punctuation(PunctType::Comma) if depth == 0 => {
+                    entries.push(self.raw(start, idx));
+                    start = idx + 1;
+                }
+                _ => {}
+            }
+        }
+        if start < args.close || !entries.is_empty() {
+            entries.push(self.raw(start, args.close - 1));
+        }
+        self.wrapped(open, entries, args.close, self.config.arguments)
+    }
+
+    /// the keyword, attributes and name of a struct, union or enum
+    fn tag(
+        &mut self,
+        keyword: TokenIdx,
+        attributes: &[Specifier],
+        name: Option<TokenIdx>,
+    ) -> Vec<Doc<'a>> {
+        let mut docs = vec![self.token(keyword)];
+        for attribute in attributes {
+            docs.push(Doc::space());
+            docs.push(self.specifier(attribute));
+        }
+        if let Some(name) = name {
+            docs.push(Doc::space());
+            docs.push(self.token(name));
+        }
+        docs
+    }
+
+    fn record(&mut self, record: &Record) -> Doc<'a> {
+        let mut docs = self.tag(record.keyword, &record.attributes, record.name);
+        if let Some(body) = &record.body {
+            let open = self.token(body.open);
+            let mut members = vec![];
+            for member in &body.members {
+                members.push(self.line_separator(members.is_empty()));
+                members.push(match member {
+                    Member::Field(declaration) => self.declaration(declaration),
+                    Member::StaticAssert(static_assert) => self.static_assert(static_assert),
+                    Member::Macro(call) => self.macro_call(call),
+                    Member::Directive(directive) => self.directive(directive),
+                });
+            }
+            let placement = self.config.braces.types;
+            docs.push(self.braced(Some(placement), open, members, body.close));
+        }
+        Doc::Concat(docs)
+    }
+
+    fn enumeration(&mut self, enumeration: &Enum) -> Doc<'a> {
+        let mut docs = self.tag(
+            enumeration.keyword,
+            &enumeration.attributes,
+            enumeration.name,
+        );
+        if let Some(body) = &enumeration.body {
+            let open = self.token(body.open);
+            // comments keep the enumerators on lines of their own
+            let one_line = self.config.enums.one_line
+                && body
+                    .entries
+                    .iter()
+                    .all(|entry| matches!(entry, EnumEntry::Enumerator(_)))
+                && !self.tokens[body.open..body.close].iter().any(|token| {
+                    matches!(
+                        token.token_type,
+                        TokenType::Comment(_) | TokenType::BlockComment(_)
+                    )
+                });
+            // the comma policy only applies to an enum ending with an enumerator, the one
+            // before a directive closing the body may not be the last one
+            let last = match body.entries.last() {
+                Some(EnumEntry::Enumerator(_)) => Some(body.entries.len() - 1),
+                _ => None,
+            };
+            let mut entries = vec![];
+            let mut trailing_comma = false;
+            for (i, entry) in body.entries.iter().enumerate() {
+                entries.push(match one_line {
+                    true => self.list_separator(entries.is_empty()),
+                    false => self.line_separator(entries.is_empty()),
+                });
+                entries.push(match entry {
+                    EnumEntry::Enumerator(enumerator) => {
+                        let (doc, comma) = self.enumerator(enumerator, Some(i) == last, body.close);
+                        trailing_comma = comma;
+                        doc
+                    }
+                    EnumEntry::Directive(directive) => self.directive(directive),
+                });
+            }
+            let placement = self.config.braces.types;
+            docs.push(match one_line {
+                true => self.braced_list(placement, open, entries, trailing_comma, body.close),
+                false => self.braced(Some(placement), open, entries, body.close),
+            });
+        }
+        Doc::Concat(docs)
+    }
+
+    /// an enumerator, along with whether it ends with a comma
+    ///
+    /// the comma after the last one is added or removed by the trailing comma policy
+    fn enumerator(
+        &mut self,
+        enumerator: &Enumerator,
+        last: bool,
+        close: TokenIdx,
+    ) -> (Doc<'a>, bool) {
+        let policy = match last {
+            true => self.config.enums.trailing_comma,
+            false => TrailingComma::Keep,
+        };
+        let tokens = self.tokens;
+        if policy == TrailingComma::Add && enumerator.comma.is_none() {
+            // the comments after the enumerator are held back to be written after the comma
+            let end = (0..close)
+                .rev()
+                .find(|idx| !tokens[*idx].token_type.is_trivia())
+                .map_or(close, |idx| idx + 1);
+            self.tokens = &tokens[..end];
+        }
+        let mut docs = vec![self.token(enumerator.name)];
+        if let Some((eq, value)) = &enumerator.value {
+            docs.push(Doc::space());
+            docs.push(self.token(*eq));
+            docs.push(Doc::space());
+            docs.push(self.expr(value));
+        }
+        self.tokens = tokens;
+        match (enumerator.comma, policy) {
+            (Some(comma), TrailingComma::Remove) => {
+                docs.push(self.trivia_before(comma));
+                self.next = comma + 1;
+                docs.push(self.trailing_comments());
+                (Doc::Concat(docs), false)
+            }
+            (Some(comma), _) => {
+                docs.push(self.token(comma));
+                (Doc::Concat(docs), true)
+            }
+            (None, TrailingComma::Add) => {
+                let location = tokens[self.next - 1].location;
+                docs.push(Doc::Token(Token::new(
+                    TokenType::Punctuation(PunctType::Comma),
+                    location,
+                )));
+                docs.push(self.trailing_comments());
+                (Doc::Concat(docs), true)
+            }
+            (None, _) => (Doc::Concat(docs), false),
+        }
+    }
+
+    /// the lines of a braced body between its braces, with the closing brace on a line of its
+    /// own
+    ///
+    /// the opening brace is placed after the code before it by `placement`, or left where it is
+    /// if there is none, as for a block standing on its own
+    pub(super) fn braced(
+        &mut self,
+        placement: Option<BracePlacement>,
+        open: Doc<'a>,
+        mut lines: Vec<Doc<'a>>,
+        close: TokenIdx,
+    ) -> Doc<'a> {
+        let separator = match placement {
+            None => Doc::Nil,
+            Some(BracePlacement::Attached) => Doc::space(),
+            Some(_) => Doc::HardLine,
+        };
+        let (braces, body) = self.brace_indents(placement);
+        lines.push(self.trivia_before(close));
+        Doc::indent(
+            braces,
+            Doc::Concat(vec![
+                separator,
+                open,
+                Doc::indent(body, Doc::Concat(lines)),
+                Doc::HardLine,
+                self.token(close),
+            ]),
+        )
+    }
+
+    /// like [`Formatter::braced`], for a list whose entries are written on one line, with
+    /// spaces inside the braces, if they fit and there is no comma after the last one
+    ///
+    /// the entries are expected to be separated by [`Formatter::list_separator`]
+    fn braced_list(
+        &mut self,
+        placement: BracePlacement,
+        open: Doc<'a>,
+        mut lines: Vec<Doc<'a>>,
+        trailing_comma: bool,
+        close: TokenIdx,
+    ) -> Doc<'a> {
+        let separator = match placement {
+            BracePlacement::Attached => Doc::space(),
+            _ => Doc::Line,
+        };
+        let (braces, body) = self.brace_indents(Some(placement));
+        if trailing_comma {
+            lines.push(Doc::HardLine);
+        }
+        lines.push(self.trivia_before(close));
+        Doc::indent(
+            braces,
+            Doc::group(
+                self.config.penalties.list,
+                Doc::Concat(vec![
+                    separator,
+                    open,
+                    Doc::indent(body, Doc::Concat(lines)),
+                    Doc::Line,
+                    self.token(close),
+                ]),
+            ),
+        )
+    }
+
+    /// the indentation of the braces of a body placed by `placement`, and of its lines
+    /// relative to the braces. the lines of blocks standing on their own are indented like the
+    /// bodies of statements
+    pub(super) fn brace_indents(&self, placement: Option<BracePlacement>) -> (usize, usize) {
+        let width = self.config.indentation.width;
+        let indents = |placement| match placement {
+            BracePlacement::Attached | BracePlacement::NextLine => (0, width),
+            BracePlacement::Gnu => (width / 2, width - width / 2),
+            BracePlacement::Whitesmiths => (width, 0),
+        };
+        match placement {
+            Some(placement) => indents(placement),
+            None => (0, indents(self.config.braces.statements).1),
+        }
+    }
+
+    pub(super) fn declarator(&mut self, declarator: &Declarator) -> Doc<'a> {
+        let mut docs = vec![];
+        for pointer in &declarator.pointers {
+            docs.push(self.token(pointer.star));
+            if !pointer.qualifiers.is_empty() {
+                docs.push(self.specifiers(&pointer.qualifiers));
+                if !matches!(declarator.direct, DirectDeclarator::Abstract) {
+                    docs.push(Doc::space());
+                }
+            }
+        }
+        docs.push(self.direct_declarator(&declarator.direct));
+        Doc::Concat(docs)
+    }
+
+    /// notes the name declared by `declarator` for alignment
+    fn declare(
+        &mut self,
+        specifiers: &[Specifier],
+        declarator: &Declarator,
+        alignment: PointerAlignment,
+    ) {
+        let (Some(first), Some(name)) = (specifiers.first(), declarator.name()) else {
+            return;
+        };
+        // the parentheses of a declarator like `(*fp)(int)` move along with the name
+        let start = match alignment {
+            PointerAlignment::Left => declarator.direct.first_token(),
+            _ => declarator.first_token(),
+        };
+        let start = start.unwrap_or(name);
+        self.marks.names.push(DeclaredName {
+            ty: self.tokens[specifier_start(first)].offset,
+            start: self.tokens[start].offset,
+            name: self.tokens[name].offset,
+        });
+    }
+
+    /// a declarator following the specifiers of its type, along with the space between them,
+    /// with the stars of its pointers placed by `alignment`
+    fn declarator_after_specifiers(
+        &mut self,
+        declarator: &Declarator,
+        alignment: PointerAlignment,
+    ) -> Doc<'a> {
+        if declarator.first_token().is_none() {
+            return self.declarator(declarator);
+        }
+        if declarator.pointers.is_empty() || alignment == PointerAlignment::Right {
+            return Doc::Concat(vec![Doc::space(), self.declarator(declarator)]);
+        }
+
+        let mut docs = vec![];
+        if alignment == PointerAlignment::Middle {
+            docs.push(Doc::space());
+        }
+        for pointer in &declarator.pointers {
+            docs.push(self.token(pointer.star));
+            if !pointer.qualifiers.is_empty() {
+                docs.push(Doc::space());
+                docs.push(self.specifiers(&pointer.qualifiers));
+            }
+        }
+        if !matches!(declarator.direct, DirectDeclarator::Abstract) {
+            docs.push(Doc::space());
+            docs.push(self.direct_declarator(&declarator.direct));
+        }
+        Doc::Concat(docs)
+    }
+
+    fn direct_declarator(&mut self, direct: &DirectDeclarator) -> Doc<'a> {
+        match direct {
+            DirectDeclarator::Abstract => Doc::Nil,
+            DirectDeclarator::Name(name) => self.token(*name),
+            DirectDeclarator::Paren { open, inner, close } => Doc::Concat(vec![
+                self.token(*open),
+                self.declarator(inner),
+                self.token(*close),
+            ]),
+            DirectDeclarator::Array {
+                inner,
+                open,
+                qualifiers,
+                size,
+                close,
+            } => {
+                let mut docs = vec![self.direct_declarator(inner), self.token(*open)];
+                let qualifiers: Vec<_> = qualifiers.iter().map(|idx| self.token(*idx)).collect();
+                let has_qualifiers = !qualifiers.is_empty();
+                docs.push(Doc::join(qualifiers, Doc::space()));
+                if let Some(size) = size {
+                    if has_qualifiers {
+                        docs.push(Doc::space());
+                    }
+                    docs.push(self.expr(size));
+                }
+                docs.push(self.token(*close));
+                Doc::Concat(docs)
+            }
+            DirectDeclarator::Function { inner, params } => Doc::Concat(vec![
+                self.direct_declarator(inner),
+                self.call_space(),
+                self.params(params),
+            ]),
+        }
+    }
+
+    fn params(&mut self, params: &ParamList) -> Doc<'a> {
+        let open = self.token(params.open);
+        let mut entries = vec![];
+        for (i, param) in params.params.iter().enumerate() {
+            let entry = match param {
+                Param::Declaration {
+                    specifiers,
+                    declarator,
+                    attributes,
+                } => {
+                    // packed parameters share their lines, so they are not lined up
+                    if !self.config.parameters.bin_pack {
+                        self.declare(specifiers, declarator, self.config.pointer_alignment);
+                    }
+                    let mut docs = vec![self.specifiers(specifiers)];
+                    docs.push(match specifiers.is_empty() {
+                        true => self.declarator(declarator),
+                        false => self
+                            .declarator_after_specifiers(declarator, self.config.pointer_alignment),
+                    });
+                    for attribute in attributes {
+                        docs.push(Doc::space());
+                        docs.push(self.specifier(attribute));
+                    }
+                    Doc::Concat(docs)
+                }
+                Param::Ellipsis(idx) => self.token(*idx),
+            };
+            entries.push(match params.commas.get(i) {
+                Some(comma) => Doc::Concat(vec![entry, self.token(*comma)]),
+                None => entry,
+            });
+        }
+        self.wrapped(open, entries, params.close, self.config.parameters)
+    }
+
+    pub(super) fn type_name(&mut self, type_name: &TypeName) -> Doc<'a> {
+        let specifiers = self.specifiers(&type_name.specifiers);
+        let declarator =
+            self.declarator_after_specifiers(&type_name.declarator, self.config.pointer_alignment);
+        Doc::Concat(vec![specifiers, declarator])
+    }
+
+    /// a function definition
+    pub(super) fn function(&mut self, function: &FunctionDef) -> Doc<'a> {
+        let mut docs = vec![self.specifiers(&function.specifiers)];
+        docs.push(match function.specifiers.is_empty() {
+            true => self.declarator(&function.declarator),
+            false => self
+                .declarator_after_specifiers(&function.declarator, self.config.pointer_alignment),
+        });
+        for attribute in &function.attributes {
+            docs.push(Doc::space());
+            docs.push(self.specifier(attribute));
+        }
+        for declaration in &function.param_declarations {
+            docs.push(Doc::HardLine);
+            docs.push(self.declaration(declaration));
+        }
+        let placement = self.config.braces.functions;
+        docs.push(self.block(&function.body, Some(placement)));
+        Doc::Concat(docs)
+    }
+
+    pub(super) fn static_assert(&mut self, static_assert: &StaticAssert) -> Doc<'a> {
+        Doc::Concat(vec![
+            self.token(static_assert.keyword),
+            self.args(&static_assert.args),
+            self.token(static_assert.semi),
+        ])
+    }
+
+    pub(super) fn macro_call(&mut self, call: &MacroCall) -> Doc<'a> {
+        let mut docs = vec![self.token(call.name)];
+        if let Some(args) = &call.args {
+            docs.push(self.call_space());
+            docs.push(self.macro_args(args));
+        }
+        if let Some(semi) = call.semi {
+            docs.push(self.token(semi));
+        }
+        Doc::Concat(docs)
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    ast::{Args, Designator, Expr, InitEntry, InitList, Selector, TokenIdx},
+    doc::Doc,
+    parser::{is_assignment, precedence},
+    OpType, TokenType, Wrapping,
+};
+
+use super::Formatter;
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    /// an expression standing on its own, whose continuation lines are indented
+    pub(super) fn full_expr(&mut self, expr: &Expr) -> Doc<'a> {
+        let doc = self.expr(expr);
+        Doc::group(self.config.penalties.list, self.continuation(doc))
+    }
+
+    /// the value following an `=`, which moves to the next line if it does not fit
+    pub(super) fn assigned(&mut self, value: &Expr) -> Doc<'a> {
+        match value {
+            Expr::InitList(_) | Expr::CompoundLiteral { .. } => {
+                Doc::Concat(vec![Doc::space(), self.expr(value)])
+            }
+            _ => {
+                let value = Doc::Concat(vec![Doc::Line, self.expr(value)]);
+                Doc::group(self.config.penalties.assignment, self.continuation(value))
+            }
+        }
+    }
+
+    fn operator(&self, idx: usize) -> Option<OpType> {
+        match self.tokens[idx].token_type {
+            TokenType::Operator(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    pub(super) fn expr(&mut self, expr: &Expr) -> Doc<'a> {
+        match expr {
+            Expr::Ident(idx) | Expr::Const(idx) => self.token(*idx),
+            Expr::Str(parts) => {
+                let parts: Vec<_> = parts.iter().map(|idx| self.token(*idx)).collect();
+                Doc::group(
+                    self.config.penalties.list,
+                    Doc::align(Doc::join(parts, Doc::Line)),
+                )
+            }
+            Expr::Paren { open, inner, close } => Doc::Concat(vec![
+                self.token(*open),
+                Doc::align(self.expr(inner)),
+                self.token(*close),
+            ]),
+            Expr::Call { callee, args } => {
+                Doc::Concat(vec![self.expr(callee), self.call_space(), self.args(args)])
+            }
+            Expr::Index {
+                base,
+                open,
+                index,
+                close,
+            } => Doc::Concat(vec![
+                self.expr(base),
+                self.token(*open),
+                self.expr(index),
+                self.token(*close),
+            ]),
+            Expr::Member { base, op, name } => {
+                Doc::Concat(vec![self.expr(base), self.token(*op), self.token(*name)])
+            }
+            Expr::Postfix { operand, op } => Doc::Concat(vec![self.expr(operand), self.token(*op)]),
+            Expr::Unary { op, operand } => {
+                let space = match self.tokens[*op].token_type {
+                    TokenType::Keyword(_) => !matches!(**operand, Expr::Paren { .. }),
+                    TokenType::Operator(op) => self.joins(op, operand),
+                    _ => false,
+                };
+                Doc::Concat(vec![
+                    self.token(*op),
+                    if space { Doc::space() } else { Doc::Nil },
+                    self.expr(operand),
+                ])
+            }
+            Expr::SizeofType {
+                keyword,
+                open,
+                type_name,
+                close,
+            } => Doc::Concat(vec![
+                self.token(*keyword),
+                self.token(*open),
+                self.type_name(type_name),
+                self.token(*close),
+            ]),
+            Expr::Cast {
+                open,
+                type_name,
+                close,
+                operand,
+            } => Doc::Concat(vec![
+                self.token(*open),
+                self.type_name(type_name),
+                self.token(*close),
+                self.expr(operand),
+            ]),
+            Expr::CompoundLiteral {
+                open,
+                type_name,
+                close,
+                init,
+            } => Doc::Concat(vec![
+                self.token(*open),
+                self.type_name(type_name),
+                self.token(*close),
+                self.init_list(init),
+            ]),
+            Expr::Generic {
+                keyword,
+                open,
+                controlling,
+                associations,
+                close,
+            } => {
+                let keyword = self.token(*keyword);
+                let open = self.token(*open);
+                let mut docs = vec![self.expr(controlling)];
+                for association in associations {
+                    docs.push(self.token(association.comma));
+                    docs.push(Doc::Line);
+                    docs.push(match &association.selector {
+                        Selector::Type(type_name) => self.type_name(type_name),
+                        Selector::Default(idx) => self.token(*idx),
+                    });
+                    docs.push(self.token(association.colon));
+                    docs.push(Doc::space());
+                    docs.push(self.expr(&association.value));
+                }
+                Doc::Concat(vec![
+                    keyword,
+                    open,
+                    Doc::group(
+                        self.config.penalties.arguments,
+                        Doc::align(Doc::Concat(docs)),
+                    ),
+                    self.token(*close),
+                ])
+            }
+            Expr::Binary { lhs, op, rhs } => match self.operator(*op) {
+                Some(assign) if is_assignment(assign) => Doc::Concat(vec![
+                    self.expr(lhs),
+                    Doc::space(),
+                    self.token(*op),
+                    self.assigned(rhs),
+                ]),
+                Some(_) => self.binary_chain(expr),
+                // the comma operator
+                None => Doc::Concat(vec![
+                    self.expr(lhs),
+                    self.token(*op),
+                    Doc::Line,
+                    self.expr(rhs),
+                ]),
+            },
+            Expr::Ternary {
+                cond,
+                question,
+                then,
+                colon,
+                otherwise,
+            } => {
+                let cond = self.expr(cond);
+                let mut docs = vec![Doc::Line, self.token(*question)];
+                if let Some(then) = then {
+                    docs.push(Doc::space());
+                    docs.push(self.expr(then));
+                    docs.push(Doc::Line);
+                }
+                docs.push(self.token(*colon));
+                docs.push(Doc::space());
+                docs.push(self.expr(otherwise));
+                // the `?` and `:` are lined up with the start of the condition
+                docs.insert(0, cond);
+                Doc::group(
+                    self.config.penalties.conditional,
+                    Doc::align(Doc::Concat(docs)),
+                )
+            }
+            Expr::InitList(init) => self.init_list(init),
+            Expr::StmtExpr { open, block, close } => Doc::Concat(vec![
+                self.token(*open),
+                self.block(block, None),
+                self.token(*close),
+            ]),
+            Expr::Type(type_name) => self.type_name(type_name),
+        }
+    }
+
+    /// whether the prefix operator `op` would run together with the start of `operand`, as in
+    /// `- -x`
+    fn joins(&self, op: OpType, operand: &Expr) -> bool {
+        let Expr::Unary { op: first, .. } = operand else {
+            return false;
+        };
+        let first = self.tokens[*first].token_type.to_str();
+        let last = op.to_str().chars().last();
+        first.starts_with(|ch| Some(ch) == last)
+    }
+
+    /// a run of binary operators of the same precedence, which is broken after every operator
+    /// or not at all
+    fn binary_chain(&mut self, expr: &Expr) -> Doc<'a> {
+        let mut operands = vec![];
+        let mut rest = expr;
+        let prec = match expr {
+            Expr::Binary { op, .. } => precedence(self.tokens[*op].token_type),
+            _ => None,
+        };
+        while let Expr::Binary { lhs, op, rhs } = rest {
+            if precedence(self.tokens[*op].token_type) != prec {
+                break;
+            }
+            operands.push((Some(*op), &**rhs));
+            rest = lhs;
+        }
+        operands.push((None, rest));
+        operands.reverse();
+
+        let mut docs = vec![];
+        for (op, operand) in operands {
+            if let Some(op) = op {
+                docs.push(Doc::space());
+                docs.push(self.token(op));
+                docs.push(Doc::Line);
+            }
+            docs.push(self.expr(operand));
+        }
+        let penalty = self.config.penalties.binary_operator * prec.unwrap_or(1) as usize;
+        Doc::group(penalty, Doc::Concat(docs))
+    }
+
+    pub(super) fn args(&mut self, args: &Args) -> Doc<'a> {
+        let open = self.token(args.open);
+        let mut entries = vec![];
+        for (i, arg) in args.args.iter().enumerate() {
+            let mut entry = vec![self.expr(arg)];
+            if let Some(comma) = args.commas.get(i) {
+                entry.push(self.token(*comma));
+            }
+            entries.push(Doc::Concat(entry));
+        }
+        self.wrapped(open, entries, args.close, self.config.arguments)
+    }
+
+    /// a parenthesized list following its opening parenthesis `open`, which is wrapped as
+    /// `wrapping` says if it does not fit. each entry but the last ends with its comma
+    pub(super) fn wrapped(
+        &mut self,
+        open: Doc<'a>,
+        entries: Vec<Doc<'a>>,
+        close: TokenIdx,
+        wrapping: Wrapping,
+    ) -> Doc<'a> {
+        let penalty = self.config.penalties.arguments;
+        if entries.is_empty() {
+            return Doc::Concat(vec![open, self.token(close)]);
+        }
+        let (separator, penalty) = match wrapping.bin_pack {
+            // packed entries are broken one at a time at the full cost, the list itself only
+            // breaks after the opening parenthesis, which is then broken whenever it does not fit
+            true => (Doc::group(penalty, Doc::Line), 0),
+            false => (Doc::Line, penalty),
+        };
+        let entries = Doc::join(entries, separator);
+        let close = self.token(close);
+        let docs = match wrapping.align_after_paren {
+            true => vec![open, Doc::align(entries), close],
+            false => {
+                // the closing parenthesis goes at the start of the line of the opening one
+                vec![Doc::hang(
+                    0,
+                    Doc::Concat(vec![
+                        open,
+                        self.continuation(Doc::Concat(vec![Doc::SoftLine, entries])),
+                        Doc::SoftLine,
+                        close,
+                    ]),
+                )]
+            }
+        };
+        Doc::group(penalty, Doc::Concat(docs))
+    }
+
+    /// a braced initializer, which is put on one line if it fits and has no trailing comma
+    fn init_list(&mut self, init: &InitList) -> Doc<'a> {
+        let open = self.token(init.open);
+        if init.entries.is_empty() {
+            let inside = self.trivia_before(init.close);
+            return Doc::Concat(vec![open, inside, self.token(init.close)]);
+        }
+
+        let mut docs = vec![Doc::Line];
+        let mut trailing_comma = false;
+        for (i, entry) in init.entries.iter().enumerate() {
+            if i > 0 {
+                docs.push(Doc::Line);
+            }
+            match entry {
+                InitEntry::Value {
+                    designators,
+                    eq,
+                    value,
+                    comma,
+                } => {
+                    for designator in designators {
+                        docs.push(match designator {
+                            Designator::Field { dot, name } => {
+                                Doc::Concat(vec![self.token(*dot), self.token(*name)])
+                            }
+                            Designator::Index { open, index, close } => Doc::Concat(vec![
+                                self.token(*open),
+                                self.expr(index),
+                                self.token(*close),
+                            ]),
+                        });
+                    }
+                    if let Some(eq) = eq {
+                        docs.push(Doc::space());
+                        docs.push(self.token(*eq));
+                        docs.push(self.assigned(value));
+                    } else {
+                        docs.push(self.expr(value));
+                    }
+                    if let Some(comma) = comma {
+                        docs.push(self.token(*comma));
+                    }
+                    trailing_comma = comma.is_some();
+                }
+                InitEntry::Directive(directive) => docs.push(self.directive(directive)),
+            }
+        }
+        if trailing_comma {
+            docs.push(Doc::HardLine);
+        }
+        docs.push(self.trivia_before(init.close));
+        Doc::group(
+            self.config.penalties.list,
+            Doc::Concat(vec![
+                open,
+                self.indent(Doc::Concat(docs)),
+                Doc::Line,
+                self.token(init.close),
+            ]),
+        )
+    }
+}
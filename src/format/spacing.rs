@@ -0,0 +1,137 @@
+use crate::{doc::Doc, OpType, ParenType, PunctType, TokenType};
+
+use super::Formatter;
+
+/// the part a token plays between the tokens around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Prefix,
+    Postfix,
+    Binary,
+    /// a `*` or `&` following a name, which is a binary operator in `a * b` but belongs to a
+    /// declarator in `int *p`
+    Ambiguous,
+    Other,
+}
+
+/// the whitespace between two tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Space {
+    None,
+    One,
+    /// whatever there is in the source
+    Source,
+}
+
+/// whether an operator following `token_type` is binary or postfix rather than prefix
+fn ends_operand(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Indentifier(_)
+            | TokenType::Const(_)
+            | TokenType::Str(_)
+            | TokenType::Paren(ParenType::RParen | ParenType::RBrack)
+    )
+}
+
+fn is_word(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Indentifier(_) | TokenType::Keyword(_) | TokenType::Const(_) | TokenType::Str(_)
+    )
+}
+
+fn role(before: Option<TokenType>, token_type: TokenType) -> Role {
+    let after_operand = before.is_some_and(ends_operand);
+    match token_type {
+        TokenType::Operator(OpType::Inc | OpType::Dec) if after_operand => Role::Postfix,
+        TokenType::Operator(OpType::Inc | OpType::Dec | OpType::Not | OpType::LNot) => Role::Prefix,
+        TokenType::Operator(OpType::Mul | OpType::LAnd)
+            if matches!(before, Some(TokenType::Indentifier(_))) =>
+        {
+            Role::Ambiguous
+        }
+        TokenType::Operator(OpType::Plus | OpType::Sub | OpType::Mul | OpType::LAnd)
+            if !after_operand =>
+        {
+            Role::Prefix
+        }
+        TokenType::Operator(_)
+        | TokenType::Punctuation(PunctType::QuestionMark | PunctType::Colon) => Role::Binary,
+        _ => Role::Other,
+    }
+}
+
+/// whether `keyword` starts a statement with a parenthesized head, like `if (x)`
+fn is_control(keyword: &str) -> bool {
+    matches!(keyword, "if" | "for" | "while" | "switch")
+}
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    /// the space between the name of a function and the parenthesis of its arguments or
+    /// parameters
+    pub(super) fn call_space(&self) -> Doc<'a> {
+        match self.config.spacing.before_call_parens {
+            true => Doc::space(),
+            false => Doc::Nil,
+        }
+    }
+
+    /// the space between a control keyword like `if` and the parenthesis following it
+    pub(super) fn control_space(&self) -> Doc<'a> {
+        match self.config.spacing.before_control_parens {
+            true => Doc::space(),
+            false => Doc::Nil,
+        }
+    }
+
+    /// the space between `prev` and `next` in code that is kept as a list of tokens, where
+    /// `before` is the token before `prev`
+    ///
+    /// binary operators are surrounded by spaces, prefix and postfix operators are attached to
+    /// their operand, and `.` and `->` to both sides. the tokens that cannot be told apart
+    /// keep their spacing
+    pub(super) fn space_between(
+        &self,
+        before: Option<TokenType>,
+        prev: TokenType,
+        next: TokenType,
+    ) -> Space {
+        let prev_role = role(before, prev);
+        let next_role = role(Some(prev), next);
+        match (prev, next) {
+            (
+                _,
+                TokenType::Punctuation(PunctType::Comma | PunctType::Semicolon)
+                | TokenType::Paren(ParenType::RParen | ParenType::RBrack),
+            )
+            | (TokenType::Paren(ParenType::LParen | ParenType::LBrack), _)
+            | (TokenType::Punctuation(PunctType::Dot | PunctType::Arrow), _)
+            | (_, TokenType::Punctuation(PunctType::Dot | PunctType::Arrow)) => Space::None,
+            (TokenType::Punctuation(PunctType::Comma | PunctType::Semicolon), _) => Space::One,
+            _ if next_role == Role::Postfix || prev_role == Role::Prefix => Space::None,
+            _ if prev_role == Role::Binary || next_role == Role::Binary => Space::One,
+            _ if prev_role == Role::Ambiguous || next_role == Role::Ambiguous => Space::Source,
+            (TokenType::Indentifier(_), TokenType::Paren(ParenType::LParen)) => {
+                match self.config.spacing.before_call_parens {
+                    true => Space::One,
+                    false => Space::None,
+                }
+            }
+            (TokenType::Keyword(keyword), TokenType::Paren(ParenType::LParen))
+                if is_control(keyword) =>
+            {
+                match self.config.spacing.before_control_parens {
+                    true => Space::One,
+                    false => Space::None,
+                }
+            }
+            (_, TokenType::Paren(ParenType::LParen | ParenType::LBrack)) if !is_word(prev) => {
+                Space::Source
+            }
+            (_, TokenType::Paren(ParenType::LParen | ParenType::LBrack)) => Space::None,
+            _ if is_word(prev) && is_word(next) => Space::One,
+            _ => Space::Source,
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use crate::{
+    ast::{Block, Condition, Expr, ForInit, Stmt, TokenIdx},
+    doc::Doc,
+    BracePlacement, CaseBlocks, GotoLabels, TokenType,
+};
+
+use super::Formatter;
+
+impl<'t, 'a> Formatter<'t, 'a> {
+    /// a block, whose opening brace is placed by `placement` as in [`Formatter::braced`]
+    pub(super) fn block(&mut self, block: &Block, placement: Option<BracePlacement>) -> Doc<'a> {
+        self.block_of(block, placement, false)
+    }
+
+    /// like [`Formatter::block`], for a block that is the body of a `switch` and where `case`
+    /// labels and the statements under them are indented as the configuration says
+    fn block_of(
+        &mut self,
+        block: &Block,
+        placement: Option<BracePlacement>,
+        switch: bool,
+    ) -> Doc<'a> {
+        let open = self.token(block.open);
+        let (_, body) = self.brace_indents(placement);
+        let labels = &self.config.labels;
+        let body = body as isize;
+        // the indentation of the statements under a label, relative to the label, which is
+        // the width of a level unless the lines of a block are at the level of its braces
+        let step = match body {
+            0 => self.config.indentation.width as isize,
+            _ => body,
+        };
+        // the indentation of case labels and of the statements, relative to the lines of the
+        // block
+        let cases = match labels.indent_cases {
+            true => 0,
+            false => -body,
+        };
+        let stmts = match (switch, labels.indent_case_bodies) {
+            (true, true) => cases + step,
+            (true, false) => cases,
+            (false, _) => 0,
+        };
+        let case_body = match labels.indent_case_bodies {
+            true => step,
+            false => 0,
+        };
+        let (case_blocks, goto_labels) = (labels.case_blocks, labels.goto_labels);
+
+        let mut lines = vec![];
+        let mut after_case = None;
+        // the label ending the body of a statement without braces, like `case 1:` in
+        // `switch (x) case 1: return 0;`, whose statement is parsed as the next one in the block
+        let mut dangling = None;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            if let (Stmt::Declaration(declaration), Some(next)) = (stmt, block.stmts.get(i + 1)) {
+                if !matches!(next, Stmt::Declaration(_) | Stmt::Directive(_)) {
+                    let semi = self.tokens[declaration.semi].offset;
+                    self.marks.declaration_ends.push(semi);
+                }
+            }
+            if let Some((label, case)) = dangling {
+                let separator = self.line_separator(lines.is_empty());
+                let stmt_doc = Doc::Concat(vec![separator, self.statement(stmt)]);
+                let shift = match stmt {
+                    Stmt::Case { .. } | Stmt::Default { .. } | Stmt::Label { .. } => label,
+                    _ if case => label + case_body,
+                    _ => label,
+                };
+                lines.push(Doc::shift(stmts + shift, stmt_doc));
+                dangling = match stmt {
+                    Stmt::Case { .. } | Stmt::Default { .. } => Some((label, true)),
+                    Stmt::Label { .. } => Some((label, false)),
+                    _ => self
+                        .dangling_label(stmt)
+                        .map(|(columns, case)| (shift + columns, case)),
+                };
+                continue;
+            }
+
+            let attached = match (stmt, after_case) {
+                (Stmt::Block(inner), Some(colon)) => {
+                    case_blocks == CaseBlocks::Attached
+                        && self.tokens[colon + 1..inner.open]
+                            .iter()
+                            .all(|token| token.token_type == TokenType::Linebreak)
+                }
+                _ => false,
+            };
+            let comments_in_body = match stmt {
+                Stmt::Case { keyword, .. } | Stmt::Default { keyword, .. } => {
+                    switch && self.comments_indented_past(*keyword)
+                }
+                _ => false,
+            };
+            let mut separator = match attached {
+                true => Doc::space(),
+                false => self.line_separator(lines.is_empty()),
+            };
+            if comments_in_body {
+                // the comments stay with the statements before the label
+                separator = Doc::Concat(vec![Doc::shift(stmts - cases, separator), Doc::HardLine]);
+            }
+            let stmt_doc = Doc::Concat(vec![separator, self.statement(stmt)]);
+            lines.push(match stmt {
+                Stmt::Case { .. } | Stmt::Default { .. } => Doc::shift(cases, stmt_doc),
+                Stmt::Label { .. } => match goto_labels {
+                    GotoLabels::FlushLeft => Doc::column(0, stmt_doc),
+                    GotoLabels::Outdented => Doc::shift(stmts - step, stmt_doc),
+                    GotoLabels::WithCode => Doc::shift(stmts, stmt_doc),
+                },
+                Stmt::Block(_) if after_case.is_some() && case_blocks != CaseBlocks::Indented => {
+                    Doc::shift(cases, stmt_doc)
+                }
+                _ => Doc::shift(stmts, stmt_doc),
+            });
+            after_case = match stmt {
+                Stmt::Case { colon, .. } | Stmt::Default { colon, .. } if switch => Some(*colon),
+                _ => None,
+            };
+            dangling = self.dangling_label(stmt);
+        }
+        self.braced(placement, open, lines, block.close)
+    }
+
+    /// the indentation of the label ending the bodies without braces of `stmt`, relative to
+    /// `stmt`, and whether it is a `case` or `default` label
+    fn dangling_label(&self, stmt: &Stmt) -> Option<(isize, bool)> {
+        let body = match stmt {
+            Stmt::If {
+                otherwise: Some((_, otherwise)),
+                ..
+            } => match **otherwise {
+                // `else if` stays on the line of the `else`
+                Stmt::If { .. } => return self.dangling_label(otherwise),
+                _ => otherwise,
+            },
+            Stmt::If { then: body, .. }
+            | Stmt::Switch { body, .. }
+            | Stmt::While { body, .. }
+            | Stmt::For { body, .. }
+            | Stmt::ForEach { body, .. } => body,
+            _ => return None,
+        };
+        let width = self.config.indentation.width as isize;
+        match **body {
+            Stmt::Block(_) => None,
+            Stmt::Case { .. } | Stmt::Default { .. } => Some((width, true)),
+            Stmt::Label { .. } => Some((width, false)),
+            _ => self
+                .dangling_label(body)
+                .map(|(columns, case)| (columns + width, case)),
+        }
+    }
+
+    /// whether the comments before the label at `label` start on a line of their own, indented
+    /// past the label in the source, like a `/* fallthrough */` ending the statements above it
+    fn comments_indented_past(&self, label: TokenIdx) -> bool {
+        (self.next..label)
+            .find(|&idx| {
+                matches!(
+                    self.tokens[idx].token_type,
+                    TokenType::Comment(_) | TokenType::BlockComment(_)
+                )
+            })
+            .is_some_and(|idx| {
+                self.starts_line(idx)
+                    && self.tokens[idx].location.col > self.tokens[label].location.col
+            })
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Doc<'a> {
+        match stmt {
+            Stmt::Directive(directive) => self.directive(directive),
+            Stmt::Verbatim(verbatim) => self.verbatim(verbatim),
+            Stmt::Declaration(declaration) => self.declaration(declaration),
+            Stmt::StaticAssert(static_assert) => self.static_assert(static_assert),
+            Stmt::Expr { expr, semi } => Doc::Concat(vec![self.full_expr(expr), self.token(*semi)]),
+            Stmt::Empty(semi) => self.token(*semi),
+            Stmt::Block(block) => self.block(block, None),
+            Stmt::If {
+                keyword,
+                cond,
+                then,
+                otherwise,
+            } => {
+                let mut docs = vec![
+                    self.token(*keyword),
+                    self.control_space(),
+                    self.condition(cond),
+                    self.body_stmt(then),
+                ];
+                if let Some((keyword, otherwise)) = otherwise {
+                    docs.push(match **then {
+                        Stmt::Block(_) if self.config.braces.cuddle_else => Doc::space(),
+                        _ => Doc::HardLine,
+                    });
+                    docs.push(self.token(*keyword));
+                    docs.push(match **otherwise {
+                        Stmt::If { .. } => {
+                            Doc::Concat(vec![Doc::space(), self.statement(otherwise)])
+                        }
+                        _ => self.body_stmt(otherwise),
+                    });
+                }
+                Doc::Concat(docs)
+            }
+            Stmt::Switch {
+                keyword,
+                cond,
+                body,
+            } => {
+                let mut docs = vec![
+                    self.token(*keyword),
+                    self.control_space(),
+                    self.condition(cond),
+                ];
+                docs.push(match &**body {
+                    Stmt::Block(block) => {
+                        self.block_of(block, Some(self.config.braces.statements), true)
+                    }
+                    _ => self.body_stmt(body),
+                });
+                Doc::Concat(docs)
+            }
+            Stmt::While {
+                keyword,
+                cond,
+                body,
+            } => Doc::Concat(vec![
+                self.token(*keyword),
+                self.control_space(),
+                self.condition(cond),
+                self.body_stmt(body),
+            ]),
+            Stmt::DoWhile {
+                do_keyword,
+                body,
+                while_keyword,
+                cond,
+                semi,
+            } => Doc::Concat(vec![
+                self.token(*do_keyword),
+                self.body_stmt(body),
+                match **body {
+                    Stmt::Block(_) if self.config.braces.cuddle_while => Doc::space(),
+                    _ => Doc::HardLine,
+                },
+                self.token(*while_keyword),
+                self.control_space(),
+                self.condition(cond),
+                self.token(*semi),
+            ]),
+            Stmt::For {
+                keyword,
+                open,
+                init,
+                cond,
+                cond_semi,
+                step,
+                close,
+                body,
+            } => {
+                let keyword = self.token(*keyword);
+                let open = self.token(*open);
+                let mut clauses = vec![match init {
+                    ForInit::Declaration(declaration) => self.declaration(declaration),
+                    ForInit::Expr(expr, semi) => {
+                        let expr = self.optional_expr(expr.as_ref());
+                        Doc::Concat(vec![expr, self.token(*semi)])
+                    }
+                }];
+                if cond.is_some() {
+                    clauses.push(Doc::Line);
+                }
+                clauses.push(self.optional_expr(cond.as_ref()));
+                clauses.push(self.token(*cond_semi));
+                if step.is_some() {
+                    clauses.push(Doc::Line);
+                }
+                clauses.push(self.optional_expr(step.as_ref()));
+                Doc::Concat(vec![
+                    keyword,
+                    self.control_space(),
+                    Doc::group(
+                        self.config.penalties.list,
+                        Doc::Concat(vec![
+                            open,
+                            Doc::align(Doc::Concat(clauses)),
+                            self.token(*close),
+                        ]),
+                    ),
+                    self.body_stmt(body),
+                ])
+            }
+            Stmt::ForEach { name, args, body } => Doc::Concat(vec![
+                self.token(*name),
+                self.control_space(),
+                self.macro_args(args),
+                self.body_stmt(body),
+            ]),
+            Stmt::Macro(call) => self.macro_call(call),
+            Stmt::Case {
+                keyword,
+                value,
+                colon,
+            } => Doc::Concat(vec![
+                self.token(*keyword),
+                Doc::space(),
+                self.expr(value),
+                self.token(*colon),
+            ]),
+            Stmt::Default { keyword, colon }
+            | Stmt::Label {
+                name: keyword,
+                colon,
+            } => Doc::Concat(vec![self.token(*keyword), self.token(*colon)]),
+            Stmt::Jump {
+                keyword,
+                value,
+                semi,
+            } => {
+                let mut docs = vec![self.token(*keyword)];
+                if let Some(value) = value {
+                    docs.push(Doc::space());
+                    docs.push(self.full_expr(value));
+                }
+                docs.push(self.token(*semi));
+                Doc::Concat(docs)
+            }
+        }
+    }
+
+    /// the body of a control statement: a block with its braces placed by the configuration,
+    /// anything else indented on the next line
+    fn body_stmt(&mut self, stmt: &Stmt) -> Doc<'a> {
+        match stmt {
+            Stmt::Block(block) => self.block(block, Some(self.config.braces.statements)),
+            _ => {
+                let lines = Doc::Concat(vec![
+                    Doc::HardLine,
+                    self.leading_comments(),
+                    self.statement(stmt),
+                ]);
+                self.indent(lines)
+            }
+        }
+    }
+
+    fn condition(&mut self, cond: &Condition) -> Doc<'a> {
+        Doc::Concat(vec![
+            self.token(cond.open),
+            Doc::group(
+                self.config.penalties.list,
+                Doc::align(self.expr(&cond.expr)),
+            ),
+            self.token(cond.close),
+        ])
+    }
+
+    fn optional_expr(&mut self, expr: Option<&Expr>) -> Doc<'a> {
+        match expr {
+            Some(expr) => self.expr(expr),
+            None => Doc::Nil,
+        }
+    }
+}
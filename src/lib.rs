@@ -1,16 +1,103 @@
 #![warn(missing_debug_implementations)]
+use std::fmt;
+
+mod ast;
+mod config;
+mod doc;
+mod doxygen;
+mod filters;
+mod format;
+mod parser;
 mod reconstruct;
+mod regex;
+mod rewrite;
 mod token;
 mod tokenizer;
-mod filters;
+mod verbatim;
 
 pub(crate) const LINE_ENDING: &str = "\n";
 
+pub use ast::*;
+pub use config::*;
+pub use parser::{parse, SyntaxError};
 pub use token::*;
 pub use tokenizer::Tokenizer;
 
+/// a problem found while formatting, which did not keep the rest of the program from being
+/// formatted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    /// where the code the warning is about starts
+    pub location: Location,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Location { line, col } = self.location;
+        write!(f, "{}:{}: {}", line + 1, col + 1, self.message)
+    }
+}
+
+/// a formatted program, along with the warnings for the parts that could not be formatted
+#[derive(Debug)]
+pub struct Formatted {
+    pub output: String,
+    pub warnings: Vec<Warning>,
+}
+
 pub fn format_string(s: String) -> String {
-    let tokenizer = Tokenizer::new(&s);
-    // TODO modify tokens
-    reconstruct::reconstruct(tokenizer)
+    format_with_config(&s, &Config::default()).output
+}
+
+/// formats the program `s` with the options in `config`
+///
+/// declarations and statements that cannot be parsed are left as they are, with a warning
+pub fn format_with_config(s: &str, config: &Config) -> Formatted {
+    let mut tokens: Vec<_> = Tokenizer::new(s).collect();
+    let mut unit = parse(&tokens, config);
+    let mut warnings = verbatim::warnings(&tokens, &unit);
+    if config.comments.doxygen {
+        warnings.extend(doxygen::warnings(&tokens, &unit));
+    }
+    if config.convert_old_style_definitions {
+        tokens = rewrite::old_style_definitions(&tokens, &unit);
+        unit = parse(&tokens, config);
+    }
+    let (doc, marks) = format::format(s, &tokens, &unit, config);
+    // the text of wrapped comments is borrowed by the rendered tokens
+    let reflow;
+    let mut rendered = doc::render(&doc, config);
+    filters::blank_lines(&mut rendered, s, &marks, config);
+    if config.includes.sort {
+        warnings.extend(filters::sort_includes(&mut rendered, &config.includes));
+    }
+    if config.alignment.declarations {
+        filters::align_names(&mut rendered.tokens, &marks.names, config);
+    }
+    if config.alignment.bitfields {
+        filters::align_bitfields(&mut rendered.tokens, &marks.bitfield_colons, config);
+    }
+    if config.alignment.assignments {
+        filters::align_eq(&mut rendered.tokens, config);
+    }
+    if config.alignment.tables {
+        filters::align_tables(&mut rendered.tokens, config);
+    }
+    filters::align_backslashes(&mut rendered.tokens, config);
+    if config.alignment.trailing_comments {
+        filters::align_comments(&mut rendered.tokens, config);
+    }
+    if config.comments.reflow || config.comments.doxygen {
+        reflow = filters::Reflow::new(&rendered.tokens, config);
+        reflow.apply(&mut rendered);
+    }
+    Formatted {
+        output: reconstruct::reconstruct_indented(
+            rendered.tokens.into_iter(),
+            &rendered.levels,
+            &config.indentation,
+        ),
+        warnings,
+    }
 }
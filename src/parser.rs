@@ -0,0 +1,413 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    ast::{Directive, Item, TokenIdx, TranslationUnit, Verbatim},
+    Config, Macros, OpType, ParenType, PunctType, Token, TokenType,
+};
+
+mod decl;
+mod expr;
+mod preprocessor;
+mod stmt;
+
+pub(crate) use expr::{is_assignment, precedence};
+use preprocessor::Conditional;
+pub(crate) use preprocessor::{directive_end, directive_kind, include_guard};
+
+const LPAREN: TokenType<'static> = TokenType::Paren(ParenType::LParen);
+const RPAREN: TokenType<'static> = TokenType::Paren(ParenType::RParen);
+const LBRACE: TokenType<'static> = TokenType::Paren(ParenType::LBrace);
+const RBRACE: TokenType<'static> = TokenType::Paren(ParenType::RBrace);
+const LBRACK: TokenType<'static> = TokenType::Paren(ParenType::LBrack);
+const RBRACK: TokenType<'static> = TokenType::Paren(ParenType::RBrack);
+const SEMI: TokenType<'static> = TokenType::Punctuation(PunctType::Semicolon);
+const COMMA: TokenType<'static> = TokenType::Punctuation(PunctType::Comma);
+const COLON: TokenType<'static> = TokenType::Punctuation(PunctType::Colon);
+const HASH: TokenType<'static> = TokenType::Punctuation(PunctType::Hash);
+const STAR: TokenType<'static> = TokenType::Operator(OpType::Mul);
+const ASSIGN: TokenType<'static> = TokenType::Operator(OpType::Assign);
+
+/// an error encountered while parsing
+#[derive(Debug)]
+pub struct SyntaxError {
+    /// the token at which parsing failed
+    pub(crate) at: TokenIdx,
+    pub(crate) kind: ErrorKind,
+}
+
+impl SyntaxError {
+    /// the token at which parsing failed
+    pub fn at(&self) -> TokenIdx {
+        self.at
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Expected(what) => write!(f, "expected {what}"),
+            ErrorKind::Directive => write!(f, "a preprocessor conditional cuts through the code"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorKind {
+    /// something else was expected at the token
+    Expected(&'static str),
+    /// a preprocessor directive cuts through a construct
+    Directive,
+}
+
+type Result<T> = std::result::Result<T, SyntaxError>;
+
+/// parses the tokens produced by a [`Tokenizer`](crate::Tokenizer)
+///
+/// top level declarations and statements that cannot be parsed, or that conditional directives
+/// cut through, are kept as verbatim regions along with the error. uses of the macros named in
+/// the config are parsed like the constructs they mimic.
+pub fn parse(tokens: &[Token], config: &Config) -> TranslationUnit {
+    Parser::new(tokens, &config.macros).translation_unit()
+}
+
+pub(crate) struct Parser<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    /// the next token that is not trivia
+    pos: TokenIdx,
+    /// names introduced with `typedef`
+    typedefs: HashSet<&'a str>,
+    /// the conditional groups, by the index of their `#if`
+    conditionals: HashMap<TokenIdx, Conditional>,
+    macros: &'t Macros,
+}
+
+impl<'t, 'a> Parser<'t, 'a> {
+    fn new(tokens: &'t [Token<'a>], macros: &'t Macros) -> Self {
+        let mut parser = Self {
+            tokens,
+            pos: 0,
+            typedefs: HashSet::new(),
+            conditionals: preprocessor::conditionals(tokens),
+            macros,
+        };
+        parser.pos = parser.skip_trivia(0);
+        parser
+    }
+
+    fn skip_trivia(&self, mut idx: TokenIdx) -> TokenIdx {
+        while self
+            .tokens
+            .get(idx)
+            .is_some_and(|token| token.token_type.is_trivia())
+        {
+            idx += 1;
+        }
+        idx
+    }
+
+    /// the end of the tokens `start..end` without the trivia at their end
+    fn trim_trivia(&self, start: TokenIdx, mut end: TokenIdx) -> TokenIdx {
+        while end > start + 1 && self.tokens[end - 1].token_type.is_trivia() {
+            end -= 1;
+        }
+        end
+    }
+
+    fn peek(&self) -> Option<TokenType<'a>> {
+        self.tokens.get(self.pos).map(|token| token.token_type)
+    }
+
+    /// the index of the `n`th token after the current one, skipping trivia
+    fn nth_idx(&self, n: usize) -> TokenIdx {
+        (0..n).fold(self.pos, |idx, _| self.skip_trivia(idx + 1))
+    }
+
+    fn nth(&self, n: usize) -> Option<TokenType<'a>> {
+        self.tokens
+            .get(self.nth_idx(n))
+            .map(|token| token.token_type)
+    }
+
+    fn at(&self, token_type: TokenType) -> bool {
+        self.peek() == Some(token_type)
+    }
+
+    fn bump(&mut self) -> TokenIdx {
+        let idx = self.pos;
+        self.pos = self.skip_trivia(idx + 1);
+        idx
+    }
+
+    fn eat(&mut self, token_type: TokenType) -> Option<TokenIdx> {
+        self.at(token_type).then(|| self.bump())
+    }
+
+    fn expect(&mut self, token_type: TokenType, what: &'static str) -> Result<TokenIdx> {
+        self.eat(token_type).ok_or_else(|| self.error(what))
+    }
+
+    fn error(&self, expected: &'static str) -> SyntaxError {
+        let kind = if self.at(HASH) {
+            ErrorKind::Directive
+        } else {
+            ErrorKind::Expected(expected)
+        };
+        SyntaxError { at: self.pos, kind }
+    }
+
+    /// parses the directive at the cursor
+    fn directive(&mut self) -> Directive {
+        let start = self.pos;
+        let end = preprocessor::directive_end(self.tokens, start);
+        self.pos = self.skip_trivia(end);
+        Directive {
+            kind: preprocessor::directive_kind(self.tokens, start),
+            start,
+            end,
+        }
+    }
+
+    /// parses the directive at the cursor in a list of declarations or statements that cannot be
+    /// split by it
+    fn directive_in_list(&mut self) -> Result<Directive> {
+        match self.conditionals.get(&self.pos) {
+            Some(conditional) if conditional.unbalanced => {
+                Err(self.error("a balanced conditional"))
+            }
+            _ => Ok(self.directive()),
+        }
+    }
+
+    /// if the cursor is at a conditional that cuts through constructs, consumes everything up to
+    /// the end of the constructs it cuts through
+    fn unbalanced_conditional(&mut self) -> Option<Verbatim> {
+        let start = self.pos;
+        if !self.conditionals.get(&start)?.unbalanced {
+            return None;
+        }
+        let end = preprocessor::extent(self.tokens, &self.conditionals, start);
+        self.pos = self.skip_trivia(end);
+        let end = self.trim_trivia(start, end);
+        let error = SyntaxError {
+            at: start,
+            kind: ErrorKind::Directive,
+        };
+        Some(Verbatim { start, end, error })
+    }
+
+    /// turns a construct starting at `start` that could not be parsed into a verbatim region,
+    /// reaching to the end of the construct or at least past the error
+    fn recover<T>(&mut self, start: TokenIdx, result: Result<T>, verbatim: fn(Verbatim) -> T) -> T {
+        let error = match result {
+            Ok(value) => return value,
+            Err(error) => error,
+        };
+        let mut end = preprocessor::extent(self.tokens, &self.conditionals, start);
+        if end <= error.at {
+            end = preprocessor::extent(self.tokens, &self.conditionals, error.at);
+        }
+        // a declaration or statement is never inside parentheses or brackets, so a `)` or `]` it
+        // stops at is stray and the construct goes on to its `;` or `}`
+        while matches!(
+            self.tokens.get(end).map(|token| token.token_type),
+            Some(TokenType::Paren(ParenType::RParen | ParenType::RBrack))
+        ) {
+            end = preprocessor::extent(self.tokens, &self.conditionals, end + 1);
+        }
+        // a stray closer ends the construct right where it starts
+        let end = end.max(start + 1);
+        self.pos = self.skip_trivia(end);
+        let end = self.trim_trivia(start, end);
+        verbatim(Verbatim { start, end, error })
+    }
+
+    fn translation_unit(&mut self) -> TranslationUnit {
+        let mut items = vec![];
+        while self.peek().is_some() {
+            if self.at(HASH) {
+                items.push(match self.unbalanced_conditional() {
+                    Some(verbatim) => Item::Verbatim(verbatim),
+                    None => Item::Directive(self.directive()),
+                });
+                continue;
+            }
+            let start = self.pos;
+            let item = self.item();
+            items.push(self.recover(start, item, Item::Verbatim));
+        }
+        TranslationUnit { items }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ast::*, Config, Tokenizer};
+
+    use super::parse;
+
+    fn items(program: &str) -> Vec<Item> {
+        let tokens: Vec<_> = Tokenizer::new(program).collect();
+        parse(&tokens, &Config::default()).items
+    }
+
+    fn function_body(item: &Item) -> &[Stmt] {
+        match item {
+            Item::Function(function) => &function.body.stmts,
+            item => panic!("expected a function, got {item:?}"),
+        }
+    }
+
+    #[test]
+    fn declarations_and_functions() {
+        let items = items(
+            "#include <stdio.h>\nstatic int a = 1, *b;\nint main(void) {\n    return a;\n}\n",
+        );
+
+        assert!(matches!(
+            items[0],
+            Item::Directive(Directive {
+                kind: DirectiveKind::Include,
+                ..
+            })
+        ));
+        match &items[1] {
+            Item::Declaration(decl) => {
+                assert_eq!(decl.specifiers.len(), 2);
+                assert_eq!(decl.declarators.len(), 2);
+                assert_eq!(decl.declarators[1].declarator.pointers.len(), 1);
+            }
+            item => panic!("expected a declaration, got {item:?}"),
+        }
+        assert!(matches!(function_body(&items[2]), [Stmt::Jump { .. }]));
+    }
+
+    #[test]
+    fn balanced_conditionals_are_parsed() {
+        let program = "void f(void) {\n#ifdef X\n    a();\n#else\n    b();\n#endif\n}\n";
+        let items = items(program);
+
+        assert!(matches!(
+            function_body(&items[0]),
+            [
+                Stmt::Directive(_),
+                Stmt::Expr { .. },
+                Stmt::Directive(_),
+                Stmt::Expr { .. },
+                Stmt::Directive(_),
+            ]
+        ));
+    }
+
+    #[test]
+    fn conditional_splitting_a_statement() {
+        let program = "void f(void) {\n#ifdef X\n    if (a) {\n#else\n    if (b) {\n#endif\n        c();\n    }\n    d();\n}\n";
+        let tokens: Vec<_> = Tokenizer::new(program).collect();
+        let items = parse(&tokens, &Config::default()).items;
+
+        match function_body(&items[0]) {
+            [Stmt::Verbatim(verbatim), Stmt::Expr { .. }] => {
+                assert_eq!(tokens[verbatim.start].location.line, 1);
+                assert_eq!(tokens[verbatim.end - 1].location.line, 7);
+            }
+            stmts => panic!("expected a verbatim region, got {stmts:?}"),
+        }
+    }
+
+    #[test]
+    fn conditional_closing_a_block() {
+        let program = "void f(void) {\n    if (a) {\n        b();\n#ifdef X\n    } else {\n        c();\n#endif\n    }\n}\n";
+        let tokens: Vec<_> = Tokenizer::new(program).collect();
+        let items = parse(&tokens, &Config::default()).items;
+
+        match function_body(&items[0]) {
+            [Stmt::If { then, .. }] => match &**then {
+                Stmt::Block(block) => match block.stmts.as_slice() {
+                    [Stmt::Expr { .. }, Stmt::Verbatim(verbatim)] => {
+                        assert_eq!(tokens[verbatim.start].location.line, 3);
+                        assert_eq!(tokens[verbatim.end - 1].location.line, 6);
+                    }
+                    stmts => panic!("expected a verbatim region, got {stmts:?}"),
+                },
+                stmt => panic!("expected a block, got {stmt:?}"),
+            },
+            stmts => panic!("expected an if statement, got {stmts:?}"),
+        }
+    }
+
+    #[test]
+    fn conditional_inside_an_expression() {
+        let program = "int x = f(a,\n#ifdef X\n    b,\n#endif\n    c);\nint y;\n";
+        let tokens: Vec<_> = Tokenizer::new(program).collect();
+        let items = parse(&tokens, &Config::default()).items;
+
+        match items.as_slice() {
+            [Item::Verbatim(verbatim), Item::Declaration(_)] => {
+                assert_eq!(tokens[verbatim.start].location.line, 0);
+                assert_eq!(tokens[verbatim.end - 1].location.line, 4);
+            }
+            items => panic!("expected a verbatim region, got {items:?}"),
+        }
+    }
+
+    #[test]
+    fn configured_macros() {
+        let mut config = Config::default();
+        config.macros.for_each.push("LIST_FOREACH".to_string());
+        config.macros.statement.push("DECLARE_BITMAP".to_string());
+        config.macros.types.push("STACK_OF".to_string());
+        config.macros.attributes.push("__user".to_string());
+        config.macros.no_semicolon.push("MODULE_INIT".to_string());
+
+        let program = "DECLARE_BITMAP(mask, 64);\nMODULE_INIT(setup)\nvoid f(STACK_OF(X509) *certs, char __user *p) {\n    LIST_FOREACH(it, list) {\n        g(it);\n    }\n    STACK_OF(X509) *copy = certs;\n}\n";
+        let tokens: Vec<_> = Tokenizer::new(program).collect();
+        let items = parse(&tokens, &config).items;
+
+        assert!(matches!(
+            items[0],
+            Item::Macro(MacroCall {
+                args: Some(_),
+                semi: Some(_),
+                ..
+            })
+        ));
+        assert!(matches!(
+            items[1],
+            Item::Macro(MacroCall { semi: None, .. })
+        ));
+        assert!(matches!(
+            function_body(&items[2]),
+            [Stmt::ForEach { .. }, Stmt::Declaration(_)]
+        ));
+    }
+
+    #[test]
+    fn c23_constructs() {
+        let program = "[[nodiscard]] int f(int x [[maybe_unused]]);\nstatic_assert(sizeof(int) == 4, \"int\");\nvoid g(void) {\n    [[fallthrough]];\n    h(_Generic(x, int: a, default: b), (struct point){ .x = 1 }.x, (point_t){ 0 });\n}\n";
+        let items = items(program);
+
+        match &items[0] {
+            Item::Declaration(decl) => {
+                assert!(matches!(decl.specifiers[0], Specifier::Attribute(_)))
+            }
+            item => panic!("expected a declaration, got {item:?}"),
+        }
+        assert!(matches!(items[1], Item::StaticAssert(_)));
+        match function_body(&items[2]) {
+            [Stmt::Declaration(_), Stmt::Expr {
+                expr: Expr::Call { args, .. },
+                ..
+            }] => assert!(matches!(
+                args.args.as_slice(),
+                [
+                    Expr::Generic { .. },
+                    Expr::Member { .. },
+                    Expr::CompoundLiteral { .. }
+                ]
+            )),
+            stmts => panic!("expected a call, got {stmts:?}"),
+        }
+    }
+}
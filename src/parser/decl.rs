@@ -0,0 +1,695 @@
+use crate::{
+    ast::{
+        Attribute, Declaration, Declarator, DirectDeclarator, Enum, EnumBody, EnumEntry,
+        Enumerator, FunctionDef, Group, InitDeclarator, Item, MacroCall, Member, Param, ParamList,
+        Pointer, Record, RecordBody, Specifier, StaticAssert, TypeName,
+    },
+    PunctType, TokenType,
+};
+
+use super::{
+    Parser, Result, ASSIGN, COLON, COMMA, HASH, LBRACE, LBRACK, LPAREN, RBRACE, RBRACK, RPAREN,
+    SEMI, STAR,
+};
+
+/// keywords naming a type
+const TYPE_KEYWORDS: &[&str] = &[
+    "void",
+    "char",
+    "short",
+    "int",
+    "long",
+    "float",
+    "double",
+    "signed",
+    "unsigned",
+    "_Bool",
+    "_Complex",
+    "_Imaginary",
+    "__signed__",
+    "bool",
+    "_Decimal32",
+    "_Decimal64",
+    "_Decimal128",
+];
+
+/// keywords that can be part of the declaration specifiers without naming a type
+const SPECIFIER_KEYWORDS: &[&str] = &[
+    "typedef",
+    "extern",
+    "static",
+    "auto",
+    "register",
+    "inline",
+    "__inline",
+    "__inline__",
+    "_Noreturn",
+    "_Thread_local",
+    "__thread",
+    "__extension__",
+    "constexpr",
+    "thread_local",
+];
+
+/// keywords qualifying a type, which may also follow a `*`
+const QUALIFIER_KEYWORDS: &[&str] = &[
+    "const",
+    "volatile",
+    "restrict",
+    "__const",
+    "__restrict",
+    "__restrict__",
+    "__volatile__",
+    "_Atomic",
+];
+
+/// keywords taking parenthesized arguments that are part of the declaration specifiers
+const CALL_KEYWORDS: &[&str] = &[
+    "__attribute__",
+    "__attribute",
+    "_Alignas",
+    "alignas",
+    "typeof",
+    "typeof_unqual",
+    "__typeof__",
+    "__typeof",
+    "_Atomic",
+    "_BitInt",
+];
+
+/// keywords taking parenthesized arguments that can follow a declarator
+const ATTRIBUTE_KEYWORDS: &[&str] = &["__attribute__", "__attribute", "asm", "__asm__", "__asm"];
+
+/// identifiers that name types without being declared in the file
+const BUILTIN_TYPES: &[&str] = &["FILE", "va_list", "jmp_buf"];
+
+impl<'t, 'a> Parser<'t, 'a> {
+    /// whether an identifier names a type, as far as can be told
+    pub(super) fn is_type_ident(&self, name: &str) -> bool {
+        self.typedefs.contains(name)
+            || name.ends_with("_t")
+            || BUILTIN_TYPES.contains(&name)
+            || self.macros.is_type(name)
+    }
+
+    /// whether the identifier at the cursor is a configured statement macro
+    pub(super) fn at_statement_macro(&self) -> bool {
+        matches!(self.peek(), Some(TokenType::Indentifier(name)) if self.macros.is_statement(name))
+    }
+
+    /// parses a use of a statement macro
+    pub(super) fn macro_call(&mut self) -> Result<MacroCall> {
+        let name = self.bump();
+        let args = match self.at(LPAREN) {
+            true => Some(self.group()?),
+            false => None,
+        };
+        let semi = match self.tokens[name].token_type {
+            TokenType::Indentifier(name) if self.macros.is_no_semicolon(name) => None,
+            _ => Some(self.expect(SEMI, "`;`")?),
+        };
+        Ok(MacroCall { name, args, semi })
+    }
+
+    /// whether the cursor is at the `[[` of a standard attribute
+    pub(super) fn at_attribute(&self) -> bool {
+        self.at(LBRACK) && self.nth(1) == Some(LBRACK)
+    }
+
+    /// parses a standard attribute like `[[nodiscard]]`, keeping its content as tokens
+    fn attribute(&mut self) -> Result<Attribute> {
+        let open = self.bump();
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                Some(LBRACK | LPAREN | LBRACE) => depth += 1,
+                Some(RBRACK) if depth == 1 && self.nth(1) == Some(RBRACK) => break,
+                Some(RBRACK | RPAREN | RBRACE) => depth -= 1,
+                Some(HASH) | None => return Err(self.error("`]]`")),
+                _ => {}
+            }
+            self.bump();
+        }
+        self.bump();
+        let close = self.bump();
+        Ok(Attribute { open, close })
+    }
+
+    /// whether the cursor is at a `static_assert`
+    pub(super) fn at_static_assert(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(TokenType::Keyword("static_assert" | "_Static_assert"))
+        )
+    }
+
+    pub(super) fn static_assert(&mut self) -> Result<StaticAssert> {
+        let keyword = self.bump();
+        let args = self.args()?;
+        let semi = self.expect(SEMI, "`;`")?;
+        Ok(StaticAssert {
+            keyword,
+            args,
+            semi,
+        })
+    }
+
+    /// whether the token at `idx` can start a type name
+    pub(super) fn starts_type_name(&self, idx: usize) -> bool {
+        match self.tokens.get(idx).map(|token| token.token_type) {
+            Some(TokenType::Keyword(keyword)) => {
+                TYPE_KEYWORDS.contains(&keyword)
+                    || QUALIFIER_KEYWORDS.contains(&keyword)
+                    || CALL_KEYWORDS.contains(&keyword)
+                    || matches!(keyword, "struct" | "union" | "enum")
+            }
+            Some(TokenType::Indentifier(name)) => self.is_type_ident(name),
+            _ => false,
+        }
+    }
+
+    /// whether the statement at the cursor is a declaration
+    pub(super) fn at_declaration(&self) -> bool {
+        if self.at_attribute() {
+            return true;
+        }
+        match self.peek() {
+            Some(TokenType::Keyword(keyword)) => {
+                TYPE_KEYWORDS.contains(&keyword)
+                    || SPECIFIER_KEYWORDS.contains(&keyword)
+                    || QUALIFIER_KEYWORDS.contains(&keyword)
+                    || CALL_KEYWORDS.contains(&keyword)
+                    || matches!(keyword, "struct" | "union" | "enum")
+            }
+            Some(TokenType::Indentifier(name))
+                if self.macros.is_type(name) || self.macros.is_attribute(name) =>
+            {
+                true
+            }
+            Some(TokenType::Indentifier(name)) => match self.nth(1) {
+                Some(TokenType::Indentifier(_)) => true,
+                Some(STAR) => self.at_pointer_declarator(1),
+                Some(TokenType::Keyword(keyword)) => QUALIFIER_KEYWORDS.contains(&keyword),
+                Some(LPAREN) => self.typedefs.contains(name) && self.nth(2) == Some(STAR),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// whether the `n`th token starts a declarator of pointer type followed by something that
+    /// can only follow a declarator, as in `foo *bar;`
+    fn at_pointer_declarator(&self, mut n: usize) -> bool {
+        while matches!(self.nth(n), Some(STAR))
+            || matches!(self.nth(n), Some(TokenType::Keyword(keyword)) if QUALIFIER_KEYWORDS.contains(&keyword))
+        {
+            n += 1;
+        }
+        matches!(self.nth(n), Some(TokenType::Indentifier(_)))
+            && matches!(
+                self.nth(n + 1),
+                Some(SEMI | COMMA | ASSIGN | LBRACK | RPAREN)
+            )
+    }
+
+    /// parses a construct at file scope
+    pub(super) fn item(&mut self) -> Result<Item> {
+        if let Some(semi) = self.eat(SEMI) {
+            return Ok(Item::Empty(semi));
+        }
+        if self.at_statement_macro() {
+            return Ok(Item::Macro(self.macro_call()?));
+        }
+        if self.at_static_assert() {
+            return Ok(Item::StaticAssert(self.static_assert()?));
+        }
+
+        let specifiers = self.specifiers()?;
+        if let Some(semi) = self.eat(SEMI) {
+            return Ok(Item::Declaration(Declaration {
+                specifiers,
+                declarators: vec![],
+                commas: vec![],
+                semi,
+            }));
+        }
+
+        let declarator = self.declarator(false)?;
+        let attributes = self.attributes()?;
+        if let Some(params) = declarator.params() {
+            let mut param_declarations = vec![];
+            if params.is_identifier_list() {
+                while self.at_declaration() {
+                    param_declarations.push(self.declaration()?);
+                }
+            }
+            if self.at(LBRACE) {
+                let body = self.block()?;
+                return Ok(Item::Function(FunctionDef {
+                    specifiers,
+                    declarator,
+                    attributes,
+                    param_declarations,
+                    body,
+                }));
+            }
+            if !param_declarations.is_empty() {
+                return Err(self.error("`{`"));
+            }
+        }
+
+        let first = self.init_declarator_rest(declarator, attributes)?;
+        Ok(Item::Declaration(self.declaration_rest(specifiers, first)?))
+    }
+
+    /// parses a declaration ending with a `;`
+    pub(super) fn declaration(&mut self) -> Result<Declaration> {
+        let specifiers = self.specifiers()?;
+        if let Some(semi) = self.eat(SEMI) {
+            return Ok(Declaration {
+                specifiers,
+                declarators: vec![],
+                commas: vec![],
+                semi,
+            });
+        }
+        let first = self.init_declarator()?;
+        self.declaration_rest(specifiers, first)
+    }
+
+    fn declaration_rest(
+        &mut self,
+        specifiers: Vec<Specifier>,
+        first: InitDeclarator,
+    ) -> Result<Declaration> {
+        let mut declarators = vec![first];
+        let mut commas = vec![];
+        while let Some(comma) = self.eat(COMMA) {
+            commas.push(comma);
+            declarators.push(self.init_declarator()?);
+        }
+        let semi = self.expect(SEMI, "`;`")?;
+
+        let is_typedef = specifiers.iter().any(|specifier| {
+            matches!(specifier, Specifier::Token(idx) if self.tokens[*idx].token_type == TokenType::Keyword("typedef"))
+        });
+        if is_typedef {
+            for declarator in &declarators {
+                if let Some(TokenType::Indentifier(name)) = declarator
+                    .declarator
+                    .name()
+                    .map(|idx| self.tokens[idx].token_type)
+                {
+                    self.typedefs.insert(name);
+                }
+            }
+        }
+
+        Ok(Declaration {
+            specifiers,
+            declarators,
+            commas,
+            semi,
+        })
+    }
+
+    fn init_declarator(&mut self) -> Result<InitDeclarator> {
+        let declarator = if self.at(COLON) {
+            // an unnamed bitfield
+            Declarator {
+                pointers: vec![],
+                direct: DirectDeclarator::Abstract,
+            }
+        } else {
+            self.declarator(false)?
+        };
+        let attributes = self.attributes()?;
+        self.init_declarator_rest(declarator, attributes)
+    }
+
+    fn init_declarator_rest(
+        &mut self,
+        declarator: Declarator,
+        mut attributes: Vec<Specifier>,
+    ) -> Result<InitDeclarator> {
+        let bitfield = match self.eat(COLON) {
+            Some(colon) => Some((colon, self.conditional()?)),
+            None => None,
+        };
+        attributes.extend(self.attributes()?);
+        let init = match self.eat(ASSIGN) {
+            Some(eq) => Some((eq, self.assignment()?)),
+            None => None,
+        };
+        Ok(InitDeclarator {
+            declarator,
+            attributes,
+            bitfield,
+            init,
+        })
+    }
+
+    /// parses the declaration specifiers, stopping at the declarator
+    pub(super) fn specifiers(&mut self) -> Result<Vec<Specifier>> {
+        let mut specifiers = vec![];
+        let mut has_type = false;
+        loop {
+            match self.peek() {
+                Some(TokenType::Keyword("struct" | "union")) => {
+                    specifiers.push(Specifier::Record(self.record()?));
+                    has_type = true;
+                }
+                Some(TokenType::Keyword("enum")) => {
+                    specifiers.push(Specifier::Enum(self.enumeration()?));
+                    has_type = true;
+                }
+                Some(TokenType::Keyword(keyword))
+                    if CALL_KEYWORDS.contains(&keyword) && self.nth(1) == Some(LPAREN) =>
+                {
+                    has_type |= !keyword.contains("attribute")
+                        && !matches!(keyword, "_Alignas" | "alignas");
+                    let keyword = self.bump();
+                    specifiers.push(Specifier::Call(keyword, self.group()?));
+                }
+                Some(TokenType::Keyword(keyword)) if TYPE_KEYWORDS.contains(&keyword) => {
+                    has_type = true;
+                    specifiers.push(Specifier::Token(self.bump()));
+                }
+                Some(TokenType::Keyword(keyword))
+                    if SPECIFIER_KEYWORDS.contains(&keyword)
+                        || QUALIFIER_KEYWORDS.contains(&keyword) =>
+                {
+                    specifiers.push(Specifier::Token(self.bump()));
+                }
+                Some(TokenType::Indentifier(name))
+                    if self.macros.is_type(name) && self.nth(1) == Some(LPAREN) =>
+                {
+                    has_type = true;
+                    let name = self.bump();
+                    specifiers.push(Specifier::Call(name, self.group()?));
+                }
+                Some(TokenType::Indentifier(name)) if self.macros.is_attribute(name) => {
+                    specifiers.push(self.attribute_macro()?);
+                }
+                Some(LBRACK) if self.at_attribute() => {
+                    specifiers.push(Specifier::Attribute(self.attribute()?));
+                }
+                Some(TokenType::Indentifier(name))
+                    if !has_type && self.ident_is_specifier(name) =>
+                {
+                    has_type = true;
+                    specifiers.push(Specifier::Token(self.bump()));
+                }
+                _ => break,
+            }
+        }
+        Ok(specifiers)
+    }
+
+    /// whether the identifier at the cursor names the type of a declaration rather than the
+    /// declared entity
+    fn ident_is_specifier(&self, name: &str) -> bool {
+        match self.nth(1) {
+            Some(TokenType::Indentifier(_) | STAR) => true,
+            Some(TokenType::Keyword(keyword)) => {
+                QUALIFIER_KEYWORDS.contains(&keyword)
+                    || SPECIFIER_KEYWORDS.contains(&keyword)
+                    || ATTRIBUTE_KEYWORDS.contains(&keyword)
+            }
+            Some(LPAREN) => self.nth(2) == Some(STAR),
+            // an unnamed bitfield, as a member needs a type
+            Some(COLON) => true,
+            Some(RPAREN | COMMA | SEMI | LBRACK) | None => self.is_type_ident(name),
+            _ => false,
+        }
+    }
+
+    /// parses a balanced pair of parentheses, keeping its content as tokens
+    pub(super) fn group(&mut self) -> Result<Group> {
+        let open = self.expect(LPAREN, "`(`")?;
+        let mut depth = 0;
+        loop {
+            match self.peek() {
+                Some(LPAREN) => depth += 1,
+                Some(RPAREN) if depth == 0 => break,
+                Some(RPAREN) => depth -= 1,
+                Some(HASH) | None => return Err(self.error("`)`")),
+                _ => {}
+            }
+            self.bump();
+        }
+        let close = self.bump();
+        Ok(Group { open, close })
+    }
+
+    /// parses the attributes and asm labels that can follow a declarator
+    pub(super) fn attributes(&mut self) -> Result<Vec<Specifier>> {
+        let mut attributes = vec![];
+        loop {
+            match self.peek() {
+                Some(TokenType::Keyword(keyword))
+                    if ATTRIBUTE_KEYWORDS.contains(&keyword) && self.nth(1) == Some(LPAREN) =>
+                {
+                    let keyword = self.bump();
+                    attributes.push(Specifier::Call(keyword, self.group()?));
+                }
+                Some(TokenType::Indentifier(name)) if self.macros.is_attribute(name) => {
+                    attributes.push(self.attribute_macro()?);
+                }
+                Some(LBRACK) if self.at_attribute() => {
+                    attributes.push(Specifier::Attribute(self.attribute()?));
+                }
+                _ => return Ok(attributes),
+            }
+        }
+    }
+
+    /// parses a use of an attribute macro, which may take arguments
+    fn attribute_macro(&mut self) -> Result<Specifier> {
+        let name = self.bump();
+        match self.at(LPAREN) {
+            true => Ok(Specifier::Call(name, self.group()?)),
+            false => Ok(Specifier::Token(name)),
+        }
+    }
+
+    fn record(&mut self) -> Result<Record> {
+        let keyword = self.bump();
+        let attributes = self.attributes()?;
+        let name = match self.peek() {
+            Some(TokenType::Indentifier(_)) => Some(self.bump()),
+            _ => None,
+        };
+        let body = match self.eat(LBRACE) {
+            Some(open) => {
+                let mut members = vec![];
+                while !self.at(RBRACE) {
+                    if self.at(HASH) {
+                        members.push(Member::Directive(self.directive_in_list()?));
+                    } else if self.at_statement_macro() {
+                        members.push(Member::Macro(self.macro_call()?));
+                    } else if self.at_static_assert() {
+                        members.push(Member::StaticAssert(self.static_assert()?));
+                    } else {
+                        members.push(Member::Field(self.declaration()?));
+                    }
+                }
+                let close = self.bump();
+                Some(RecordBody {
+                    open,
+                    members,
+                    close,
+                })
+            }
+            None if name.is_none() => return Err(self.error("a name or `{`")),
+            None => None,
+        };
+        Ok(Record {
+            keyword,
+            attributes,
+            name,
+            body,
+        })
+    }
+
+    fn enumeration(&mut self) -> Result<Enum> {
+        let keyword = self.bump();
+        let attributes = self.attributes()?;
+        let name = match self.peek() {
+            Some(TokenType::Indentifier(_)) => Some(self.bump()),
+            _ => None,
+        };
+        let body = match self.eat(LBRACE) {
+            Some(open) => {
+                let mut entries = vec![];
+                while !self.at(RBRACE) {
+                    if self.at(HASH) {
+                        entries.push(EnumEntry::Directive(self.directive_in_list()?));
+                        continue;
+                    }
+                    let name = match self.peek() {
+                        Some(TokenType::Indentifier(_)) => self.bump(),
+                        _ => return Err(self.error("an enumerator")),
+                    };
+                    let value = match self.eat(ASSIGN) {
+                        Some(eq) => Some((eq, self.conditional()?)),
+                        None => None,
+                    };
+                    let comma = self.eat(COMMA);
+                    let last = comma.is_none();
+                    entries.push(EnumEntry::Enumerator(Enumerator { name, value, comma }));
+                    if last && !self.at(RBRACE) && !self.at(HASH) {
+                        return Err(self.error("`,` or `}`"));
+                    }
+                }
+                let close = self.bump();
+                Some(EnumBody {
+                    open,
+                    entries,
+                    close,
+                })
+            }
+            None if name.is_none() => return Err(self.error("a name or `{`")),
+            None => None,
+        };
+        Ok(Enum {
+            keyword,
+            attributes,
+            name,
+            body,
+        })
+    }
+
+    /// parses a declarator. abstract declarators without a name are accepted if `is_abstract`
+    pub(super) fn declarator(&mut self, is_abstract: bool) -> Result<Declarator> {
+        let mut pointers = vec![];
+        while let Some(star) = self.eat(STAR) {
+            let mut qualifiers = vec![];
+            loop {
+                match self.peek() {
+                    Some(TokenType::Keyword(keyword))
+                        if ATTRIBUTE_KEYWORDS.contains(&keyword) && self.nth(1) == Some(LPAREN) =>
+                    {
+                        let keyword = self.bump();
+                        qualifiers.push(Specifier::Call(keyword, self.group()?));
+                    }
+                    Some(TokenType::Keyword(keyword)) if QUALIFIER_KEYWORDS.contains(&keyword) => {
+                        qualifiers.push(Specifier::Token(self.bump()));
+                    }
+                    Some(TokenType::Indentifier(name)) if self.macros.is_attribute(name) => {
+                        qualifiers.push(self.attribute_macro()?);
+                    }
+                    _ => break,
+                }
+            }
+            pointers.push(Pointer { star, qualifiers });
+        }
+        let direct = self.direct_declarator(is_abstract)?;
+        Ok(Declarator { pointers, direct })
+    }
+
+    fn direct_declarator(&mut self, is_abstract: bool) -> Result<DirectDeclarator> {
+        let mut direct = match self.peek() {
+            Some(TokenType::Indentifier(_)) => DirectDeclarator::Name(self.bump()),
+            Some(LPAREN) if self.at_nested_declarator(is_abstract) => {
+                let open = self.bump();
+                let inner = Box::new(self.declarator(is_abstract)?);
+                let close = self.expect(RPAREN, "`)`")?;
+                DirectDeclarator::Paren { open, inner, close }
+            }
+            _ if is_abstract => DirectDeclarator::Abstract,
+            _ => return Err(self.error("a declarator")),
+        };
+
+        loop {
+            if self.at(LBRACK) && !self.at_attribute() {
+                let open = self.bump();
+                let mut qualifiers = vec![];
+                while matches!(self.peek(), Some(TokenType::Keyword(keyword)) if keyword == "static" || QUALIFIER_KEYWORDS.contains(&keyword))
+                    || (self.at(STAR) && self.nth(1) == Some(RBRACK))
+                {
+                    qualifiers.push(self.bump());
+                }
+                let size = match self.at(RBRACK) {
+                    true => None,
+                    false => Some(self.assignment()?),
+                };
+                let close = self.expect(RBRACK, "`]`")?;
+                direct = DirectDeclarator::Array {
+                    inner: Box::new(direct),
+                    open,
+                    qualifiers,
+                    size,
+                    close,
+                };
+            } else if self.at(LPAREN) {
+                let params = self.param_list()?;
+                direct = DirectDeclarator::Function {
+                    inner: Box::new(direct),
+                    params,
+                };
+            } else {
+                return Ok(direct);
+            }
+        }
+    }
+
+    /// whether the `(` at the cursor opens a nested declarator rather than a parameter list
+    fn at_nested_declarator(&self, is_abstract: bool) -> bool {
+        match self.nth(1) {
+            Some(STAR | LPAREN | LBRACK) => true,
+            Some(TokenType::Operator(crate::OpType::LXor)) => true,
+            Some(TokenType::Indentifier(name)) => !is_abstract && !self.is_type_ident(name),
+            _ => false,
+        }
+    }
+
+    fn param_list(&mut self) -> Result<ParamList> {
+        let open = self.expect(LPAREN, "`(`")?;
+        let mut params = vec![];
+        let mut commas = vec![];
+        if !self.at(RPAREN) {
+            loop {
+                if let Some(ellipsis) = self.eat(TokenType::Punctuation(PunctType::Ellipsis)) {
+                    params.push(Param::Ellipsis(ellipsis));
+                } else {
+                    let specifiers = self.specifiers()?;
+                    let declarator = self.declarator(true)?;
+                    let attributes = self.attributes()?;
+                    params.push(Param::Declaration {
+                        specifiers,
+                        declarator,
+                        attributes,
+                    });
+                }
+                match self.eat(COMMA) {
+                    Some(comma) => commas.push(comma),
+                    None => break,
+                }
+            }
+        }
+        let close = self.expect(RPAREN, "`)`")?;
+        Ok(ParamList {
+            open,
+            params,
+            commas,
+            close,
+        })
+    }
+
+    pub(super) fn type_name(&mut self) -> Result<TypeName> {
+        let mut specifiers = self.specifiers()?;
+        if specifiers.is_empty() {
+            // a name that is used like a type without being known as one
+            match self.peek() {
+                Some(TokenType::Indentifier(_)) => specifiers.push(Specifier::Token(self.bump())),
+                _ => return Err(self.error("a type")),
+            }
+        }
+        let declarator = self.declarator(true)?;
+        Ok(TypeName {
+            specifiers,
+            declarator,
+        })
+    }
+}
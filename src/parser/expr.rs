@@ -0,0 +1,411 @@
+use crate::{
+    ast::{Args, Association, Designator, Expr, InitEntry, InitList, Selector},
+    OpType, ParenType, PunctType, TokenType,
+};
+
+use super::{
+    Parser, Result, ASSIGN, COLON, COMMA, HASH, LBRACE, LBRACK, LPAREN, RBRACE, RBRACK, RPAREN,
+    STAR,
+};
+
+/// the precedence of a binary operator, higher binds tighter
+pub(crate) fn precedence(token_type: TokenType) -> Option<u8> {
+    use OpType::*;
+    match token_type {
+        TokenType::Operator(op) => Some(match op {
+            Or => 1,
+            And => 2,
+            LOr => 3,
+            LXor => 4,
+            LAnd => 5,
+            Eq | Neq => 6,
+            Lt | Gt | Le | Ge => 7,
+            Shl | Shr => 8,
+            Plus | Sub => 9,
+            Mul | Div | Mod => 10,
+            _ => return None,
+        }),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_assignment(op: OpType) -> bool {
+    use OpType::*;
+    matches!(
+        op,
+        Assign
+            | PlusAssign
+            | SubAssign
+            | MulAssign
+            | DivAssign
+            | ShlAssign
+            | ShrAssign
+            | ModAssign
+            | LAndAssign
+            | LOrAssign
+            | LXorAssign
+            | LNotAssign
+    )
+}
+
+impl<'t, 'a> Parser<'t, 'a> {
+    /// parses an expression, including the comma operator
+    pub(super) fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.assignment()?;
+        while let Some(op) = self.eat(COMMA) {
+            let rhs = self.assignment()?;
+            lhs = Expr::Binary {
+                lhs: Box::new(lhs),
+                op,
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    pub(super) fn assignment(&mut self) -> Result<Expr> {
+        let lhs = self.conditional()?;
+        match self.peek() {
+            Some(TokenType::Operator(op)) if is_assignment(op) => {
+                let op = self.bump();
+                let rhs = self.assignment()?;
+                Ok(Expr::Binary {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs: Box::new(rhs),
+                })
+            }
+            _ => Ok(lhs),
+        }
+    }
+
+    pub(super) fn conditional(&mut self) -> Result<Expr> {
+        let cond = self.binary(1)?;
+        let Some(question) = self.eat(TokenType::Punctuation(PunctType::QuestionMark)) else {
+            return Ok(cond);
+        };
+        let then = match self.at(COLON) {
+            true => None,
+            false => Some(Box::new(self.expr()?)),
+        };
+        let colon = self.expect(COLON, "`:`")?;
+        let otherwise = self.conditional()?;
+        Ok(Expr::Ternary {
+            cond: Box::new(cond),
+            question,
+            then,
+            colon,
+            otherwise: Box::new(otherwise),
+        })
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Result<Expr> {
+        let mut lhs = self.cast()?;
+        while let Some(prec) = self
+            .peek()
+            .and_then(precedence)
+            .filter(|prec| *prec >= min_precedence)
+        {
+            let op = self.bump();
+            let rhs = self.binary(prec + 1)?;
+            lhs = Expr::Binary {
+                lhs: Box::new(lhs),
+                op,
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn cast(&mut self) -> Result<Expr> {
+        if !self.at_cast() {
+            return self.unary();
+        }
+        let open = self.bump();
+        let type_name = Box::new(self.type_name()?);
+        let close = self.expect(RPAREN, "`)`")?;
+        if self.at(LBRACE) {
+            let literal = Expr::CompoundLiteral {
+                open,
+                type_name,
+                close,
+                init: self.init_list()?,
+            };
+            return self.postfix_of(literal);
+        }
+        let operand = Box::new(self.cast()?);
+        Ok(Expr::Cast {
+            open,
+            type_name,
+            close,
+            operand,
+        })
+    }
+
+    /// whether the `(` at the cursor starts a cast rather than a parenthesized expression
+    fn at_cast(&self) -> bool {
+        if !self.at(LPAREN) {
+            return false;
+        }
+        if self.starts_type_name(self.nth_idx(1)) {
+            return true;
+        }
+        if !matches!(self.nth(1), Some(TokenType::Indentifier(_))) {
+            return false;
+        }
+
+        // an unknown name followed by stars, or by something that can only be an operand
+        let mut n = 2;
+        while self.nth(n) == Some(STAR) {
+            n += 1;
+        }
+        if self.nth(n) != Some(RPAREN) {
+            return false;
+        }
+        n > 2
+            || matches!(
+                self.nth(n + 1),
+                Some(
+                    TokenType::Indentifier(_)
+                        | TokenType::Const(_)
+                        | TokenType::Str(_)
+                        | TokenType::Keyword("sizeof")
+                        | TokenType::Paren(ParenType::LBrace)
+                        | TokenType::Operator(OpType::LNot | OpType::Not)
+                )
+            )
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        use OpType::*;
+        match self.peek() {
+            Some(TokenType::Operator(Inc | Dec | LAnd | Mul | Plus | Sub | LNot | Not | And)) => {
+                let op = self.bump();
+                let operand = Box::new(self.cast()?);
+                Ok(Expr::Unary { op, operand })
+            }
+            Some(TokenType::Keyword("sizeof" | "alignof" | "_Alignof" | "__alignof__")) => {
+                if self.nth(1) == Some(LPAREN) && self.starts_type_name(self.nth_idx(2)) {
+                    let keyword = self.bump();
+                    let open = self.bump();
+                    let type_name = Box::new(self.type_name()?);
+                    let close = self.expect(RPAREN, "`)`")?;
+                    return Ok(Expr::SizeofType {
+                        keyword,
+                        open,
+                        type_name,
+                        close,
+                    });
+                }
+                let op = self.bump();
+                let operand = Box::new(self.unary()?);
+                Ok(Expr::Unary { op, operand })
+            }
+            Some(TokenType::Keyword("__extension__")) => {
+                let op = self.bump();
+                let operand = Box::new(self.cast()?);
+                Ok(Expr::Unary { op, operand })
+            }
+            _ => self.postfix(),
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let expr = self.primary()?;
+        self.postfix_of(expr)
+    }
+
+    /// parses the postfix operators applied to `expr`
+    fn postfix_of(&mut self, mut expr: Expr) -> Result<Expr> {
+        loop {
+            expr = match self.peek() {
+                Some(LBRACK) => {
+                    let open = self.bump();
+                    let index = Box::new(self.expr()?);
+                    let close = self.expect(RBRACK, "`]`")?;
+                    Expr::Index {
+                        base: Box::new(expr),
+                        open,
+                        index,
+                        close,
+                    }
+                }
+                Some(LPAREN) => Expr::Call {
+                    callee: Box::new(expr),
+                    args: self.args()?,
+                },
+                Some(TokenType::Punctuation(PunctType::Dot | PunctType::Arrow)) => {
+                    let op = self.bump();
+                    let name = match self.peek() {
+                        Some(TokenType::Indentifier(_)) => self.bump(),
+                        _ => return Err(self.error("a member name")),
+                    };
+                    Expr::Member {
+                        base: Box::new(expr),
+                        op,
+                        name,
+                    }
+                }
+                Some(TokenType::Operator(OpType::Inc | OpType::Dec)) => Expr::Postfix {
+                    operand: Box::new(expr),
+                    op: self.bump(),
+                },
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(TokenType::Indentifier(_)) => Ok(Expr::Ident(self.bump())),
+            Some(TokenType::Const(_) | TokenType::Keyword("true" | "false" | "nullptr")) => {
+                Ok(Expr::Const(self.bump()))
+            }
+            Some(TokenType::Keyword("_Generic")) => self.generic(),
+            Some(TokenType::Str(_)) => {
+                let mut parts = vec![self.bump()];
+                loop {
+                    match (self.peek(), self.nth(1)) {
+                        (Some(TokenType::Str(_)), _)
+                        | (Some(TokenType::Indentifier(_)), Some(TokenType::Str(_))) => {
+                            parts.push(self.bump())
+                        }
+                        _ => return Ok(Expr::Str(parts)),
+                    }
+                }
+            }
+            Some(LPAREN) if self.nth(1) == Some(LBRACE) => {
+                let open = self.bump();
+                let block = self.block()?;
+                let close = self.expect(RPAREN, "`)`")?;
+                Ok(Expr::StmtExpr { open, block, close })
+            }
+            Some(LPAREN) => {
+                let open = self.bump();
+                let inner = Box::new(self.expr()?);
+                let close = self.expect(RPAREN, "`)`")?;
+                Ok(Expr::Paren { open, inner, close })
+            }
+            Some(LBRACE) => Ok(Expr::InitList(self.init_list()?)),
+            _ => Err(self.error("an expression")),
+        }
+    }
+
+    /// parses a generic selection like `_Generic(x, int: f, default: g)`
+    fn generic(&mut self) -> Result<Expr> {
+        let keyword = self.bump();
+        let open = self.expect(LPAREN, "`(`")?;
+        let controlling = Box::new(self.assignment()?);
+        let mut associations = vec![];
+        while let Some(comma) = self.eat(COMMA) {
+            let selector = match self.eat(TokenType::Keyword("default")) {
+                Some(default) => Selector::Default(default),
+                None => Selector::Type(self.type_name()?),
+            };
+            let colon = self.expect(COLON, "`:`")?;
+            let value = self.assignment()?;
+            associations.push(Association {
+                comma,
+                selector,
+                colon,
+                value,
+            });
+        }
+        let close = self.expect(RPAREN, "`)`")?;
+        Ok(Expr::Generic {
+            keyword,
+            open,
+            controlling,
+            associations,
+            close,
+        })
+    }
+
+    /// parses the arguments of a call, which may be types for calls of macros
+    pub(super) fn args(&mut self) -> Result<Args> {
+        let open = self.expect(LPAREN, "`(`")?;
+        let mut args = vec![];
+        let mut commas = vec![];
+        if !self.at(RPAREN) {
+            loop {
+                args.push(self.argument()?);
+                match self.eat(COMMA) {
+                    Some(comma) => commas.push(comma),
+                    None => break,
+                }
+            }
+        }
+        let close = self.expect(RPAREN, "`)`")?;
+        Ok(Args {
+            open,
+            args,
+            commas,
+            close,
+        })
+    }
+
+    fn argument(&mut self) -> Result<Expr> {
+        let is_type = match self.peek() {
+            Some(TokenType::Keyword(_)) => self.starts_type_name(self.pos),
+            Some(TokenType::Indentifier(name)) => {
+                (self.is_type_ident(name) && matches!(self.nth(1), Some(RPAREN | COMMA | STAR)))
+                    || (self.macros.is_type(name) && self.nth(1) == Some(LPAREN))
+            }
+            _ => false,
+        };
+        match is_type {
+            true => Ok(Expr::Type(Box::new(self.type_name()?))),
+            false => self.assignment(),
+        }
+    }
+
+    pub(super) fn init_list(&mut self) -> Result<InitList> {
+        let open = self.expect(LBRACE, "`{`")?;
+        let mut entries = vec![];
+        while !self.at(RBRACE) {
+            if self.at(HASH) {
+                entries.push(InitEntry::Directive(self.directive_in_list()?));
+                continue;
+            }
+
+            let mut designators = vec![];
+            loop {
+                if let Some(dot) = self.eat(TokenType::Punctuation(PunctType::Dot)) {
+                    let name = match self.peek() {
+                        Some(TokenType::Indentifier(_)) => self.bump(),
+                        _ => return Err(self.error("a member name")),
+                    };
+                    designators.push(Designator::Field { dot, name });
+                } else if let Some(open) = self.eat(LBRACK) {
+                    let index = self.conditional()?;
+                    let close = self.expect(RBRACK, "`]`")?;
+                    designators.push(Designator::Index { open, index, close });
+                } else {
+                    break;
+                }
+            }
+            let eq = match designators.is_empty() {
+                true => None,
+                false => Some(self.expect(ASSIGN, "`=`")?),
+            };
+            let value = self.assignment()?;
+            let comma = self.eat(COMMA);
+            let last = comma.is_none();
+            entries.push(InitEntry::Value {
+                designators,
+                eq,
+                value,
+                comma,
+            });
+            if last && !self.at(RBRACE) && !self.at(HASH) {
+                return Err(self.error("`,` or `}`"));
+            }
+        }
+        let close = self.bump();
+        Ok(InitList {
+            open,
+            entries,
+            close,
+        })
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{DirectiveKind, TokenIdx},
+    ParenType, PunctType, Token, TokenType,
+};
+
+/// a `#if` ... `#endif` group
+#[derive(Debug)]
+pub(crate) struct Conditional {
+    /// the `#` of the `#endif`
+    pub(crate) endif: TokenIdx,
+    /// the largest change of the nesting depth over the branches
+    pub(crate) depth: isize,
+    /// whether a branch opens or closes constructs that it does not close or open itself, or the
+    /// group is otherwise unfit to be parsed, like `#if 0`
+    pub(crate) unbalanced: bool,
+}
+
+/// the token ending the directive starting at `start`: the first line break that is not escaped,
+/// or the end of the tokens
+pub(crate) fn directive_end(tokens: &[Token], start: TokenIdx) -> TokenIdx {
+    (start + 1..tokens.len())
+        .find(|&idx| {
+            tokens[idx].token_type == TokenType::Linebreak
+                && tokens[idx - 1].token_type != TokenType::LineContinuation
+        })
+        .unwrap_or(tokens.len())
+}
+
+/// the text of the directive starting at `start`, following the `#`
+fn directive_text<'a>(tokens: &[Token<'a>], start: TokenIdx) -> &'a str {
+    match tokens.get(start + 1).map(|token| token.token_type) {
+        Some(TokenType::Directive(text)) => text,
+        _ => "",
+    }
+}
+
+pub(crate) fn directive_kind(tokens: &[Token], start: TokenIdx) -> DirectiveKind {
+    let text = directive_text(tokens, start);
+    let name = text
+        .split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .next()
+        .unwrap_or_default();
+    match name {
+        "if" | "ifdef" | "ifndef" => DirectiveKind::If,
+        "elif" | "elifdef" | "elifndef" => DirectiveKind::Elif,
+        "else" => DirectiveKind::Else,
+        "endif" => DirectiveKind::Endif,
+        "include" | "include_next" | "import" => DirectiveKind::Include,
+        "define" => DirectiveKind::Define,
+        _ => DirectiveKind::Other,
+    }
+}
+
+fn depth_change(token_type: TokenType) -> isize {
+    match token_type {
+        TokenType::Paren(ParenType::LParen | ParenType::LBrace | ParenType::LBrack) => 1,
+        TokenType::Paren(ParenType::RParen | ParenType::RBrace | ParenType::RBrack) => -1,
+        _ => 0,
+    }
+}
+
+/// a conditional group whose `#endif` has not been seen yet
+struct OpenConditional {
+    start: TokenIdx,
+    disabled: bool,
+    has_else: bool,
+    /// the change of the nesting depth and its minimum, for each branch seen so far
+    branches: Vec<(isize, isize)>,
+    depth: isize,
+    min_depth: isize,
+}
+
+/// finds all conditional groups, by the index of their `#if`
+pub(crate) fn conditionals(tokens: &[Token]) -> HashMap<TokenIdx, Conditional> {
+    let mut conditionals = HashMap::new();
+    let mut open: Vec<OpenConditional> = vec![];
+
+    let mut idx = 0;
+    while idx < tokens.len() {
+        if tokens[idx].token_type != TokenType::Punctuation(PunctType::Hash) {
+            if let Some(top) = open.last_mut() {
+                top.depth += depth_change(tokens[idx].token_type);
+                top.min_depth = top.min_depth.min(top.depth);
+            }
+            idx += 1;
+            continue;
+        }
+
+        match directive_kind(tokens, idx) {
+            DirectiveKind::If => open.push(OpenConditional {
+                start: idx,
+                disabled: directive_text(tokens, idx)
+                    .strip_prefix("if")
+                    .is_some_and(|cond| cond.trim() == "0"),
+                has_else: false,
+                branches: vec![],
+                depth: 0,
+                min_depth: 0,
+            }),
+            kind @ (DirectiveKind::Elif | DirectiveKind::Else) => {
+                if let Some(top) = open.last_mut() {
+                    top.branches.push((top.depth, top.min_depth));
+                    top.has_else |= kind == DirectiveKind::Else;
+                    top.depth = 0;
+                    top.min_depth = 0;
+                }
+            }
+            DirectiveKind::Endif => {
+                if let Some(mut top) = open.pop() {
+                    top.branches.push((top.depth, top.min_depth));
+                    if !top.has_else {
+                        top.branches.push((0, 0));
+                    }
+                    let depth = top
+                        .branches
+                        .iter()
+                        .map(|(depth, _)| *depth)
+                        .max()
+                        .unwrap_or(0);
+                    let min_depth = top.branches.iter().map(|(_, min)| *min).min().unwrap_or(0);
+                    let unbalanced = top.disabled
+                        || top
+                            .branches
+                            .iter()
+                            .any(|&(depth, min)| depth != 0 || min < 0);
+                    conditionals.insert(
+                        top.start,
+                        Conditional {
+                            endif: idx,
+                            depth,
+                            unbalanced,
+                        },
+                    );
+                    if let Some(parent) = open.last_mut() {
+                        parent.min_depth = parent.min_depth.min(parent.depth + min_depth);
+                        parent.depth += depth;
+                    }
+                }
+            }
+            _ => {}
+        }
+        idx = directive_end(tokens, idx);
+    }
+
+    conditionals
+}
+
+/// the first token that is not trivia at or after `idx`
+fn skip_trivia(tokens: &[Token], mut idx: TokenIdx) -> TokenIdx {
+    while tokens
+        .get(idx)
+        .is_some_and(|token| token.token_type.is_trivia())
+    {
+        idx += 1;
+    }
+    idx
+}
+
+/// the `#` of the `#ifndef` and of the `#endif` of an include guard around all of the code,
+/// like `#ifndef NAME` `#define NAME` ... `#endif`
+pub(crate) fn include_guard(tokens: &[Token]) -> Option<(TokenIdx, TokenIdx)> {
+    let start = skip_trivia(tokens, 0);
+    let define = skip_trivia(tokens, directive_end(tokens, start));
+    let hash = TokenType::Punctuation(PunctType::Hash);
+    if tokens.get(start)?.token_type != hash || tokens.get(define)?.token_type != hash {
+        return None;
+    }
+    let name = directive_text(tokens, start).strip_prefix("ifndef")?.trim();
+    let defined = directive_text(tokens, define).strip_prefix("define")?;
+    if name.is_empty() || defined.split_whitespace().next() != Some(name) {
+        return None;
+    }
+    let endif = conditionals(tokens).get(&start)?.endif;
+    let end = skip_trivia(tokens, directive_end(tokens, endif));
+    (end == tokens.len()).then_some((start, endif))
+}
+
+/// the significant token following `idx`
+fn next_significant<'a>(tokens: &[Token<'a>], mut idx: TokenIdx) -> Option<TokenType<'a>> {
+    idx += 1;
+    while let Some(token) = tokens.get(idx) {
+        if !token.token_type.is_trivia() {
+            return Some(token.token_type);
+        }
+        idx += 1;
+    }
+    None
+}
+
+fn previous_significant<'a>(tokens: &[Token<'a>], idx: TokenIdx) -> Option<TokenType<'a>> {
+    tokens[..idx]
+        .iter()
+        .rev()
+        .map(|token| token.token_type)
+        .find(|token_type| !token_type.is_trivia())
+}
+
+/// finds the end of the statement or top level item starting at `start`, without parsing it
+///
+/// conditional groups are skipped as a whole, assuming the branch that opens the most constructs.
+/// the returned index is one past the last token of the construct
+pub(crate) fn extent(
+    tokens: &[Token],
+    conditionals: &HashMap<TokenIdx, Conditional>,
+    start: TokenIdx,
+) -> TokenIdx {
+    let mut depth = 0;
+    // whether the outermost braces end the construct, as opposed to a struct body or an
+    // initializer that is followed by declarators or a `;`
+    let mut braces_end = true;
+    let mut pending_do = 0;
+
+    let mut idx = start;
+    while idx < tokens.len() {
+        let token_type = tokens[idx].token_type;
+        if token_type == TokenType::Punctuation(PunctType::Hash) {
+            match conditionals.get(&idx) {
+                Some(conditional) => {
+                    let first = idx == start;
+                    depth += conditional.depth;
+                    idx = directive_end(tokens, conditional.endif);
+                    if first && depth <= 0 {
+                        return idx;
+                    }
+                }
+                None => idx = directive_end(tokens, idx),
+            }
+            continue;
+        }
+
+        match token_type {
+            TokenType::Paren(ParenType::LBrace) if depth == 0 => {
+                braces_end = !matches!(
+                    previous_significant(tokens, idx),
+                    Some(
+                        TokenType::Operator(_)
+                            | TokenType::Indentifier(_)
+                            | TokenType::Keyword("struct" | "union" | "enum")
+                    )
+                );
+                depth += 1;
+            }
+            TokenType::Paren(ParenType::RParen | ParenType::RBrace | ParenType::RBrack)
+                if depth <= 0 =>
+            {
+                // the end of the enclosing construct
+                return idx;
+            }
+            TokenType::Paren(ParenType::RBrace) if depth == 1 => {
+                depth = 0;
+                let next = next_significant(tokens, idx);
+                if braces_end && pending_do == 0 && next != Some(TokenType::Keyword("else")) {
+                    return idx + 1;
+                }
+            }
+            TokenType::Punctuation(PunctType::Semicolon) if depth == 0 => {
+                let next = next_significant(tokens, idx);
+                if pending_do == 0 && next != Some(TokenType::Keyword("else")) {
+                    return idx + 1;
+                }
+            }
+            TokenType::Keyword("do") if depth == 0 => pending_do += 1,
+            TokenType::Keyword("while") if depth == 0 && pending_do > 0 => pending_do -= 1,
+            token_type => depth += depth_change(token_type),
+        }
+        idx += 1;
+    }
+    tokens.len()
+}
@@ -0,0 +1,190 @@
+use crate::{
+    ast::{Block, Condition, Expr, ForInit, Stmt},
+    PunctType, TokenType,
+};
+
+use super::{Parser, Result, COLON, HASH, LBRACE, LPAREN, RBRACE, RPAREN, SEMI};
+
+impl<'t, 'a> Parser<'t, 'a> {
+    pub(super) fn block(&mut self) -> Result<Block> {
+        let open = self.expect(LBRACE, "`{`")?;
+        let stmts = self.statement_list();
+        let close = self.expect(RBRACE, "`}`")?;
+        Ok(Block { open, stmts, close })
+    }
+
+    /// parses statements up to the closing `}` of the enclosing block
+    fn statement_list(&mut self) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        while self.peek().is_some() && !self.at(RBRACE) {
+            if self.at(HASH) {
+                stmts.push(match self.unbalanced_conditional() {
+                    Some(verbatim) => Stmt::Verbatim(verbatim),
+                    None => Stmt::Directive(self.directive()),
+                });
+                continue;
+            }
+            let start = self.pos;
+            let stmt = self.statement();
+            stmts.push(self.recover(start, stmt, Stmt::Verbatim));
+        }
+        stmts
+    }
+
+    fn statement(&mut self) -> Result<Stmt> {
+        match self.peek() {
+            Some(LBRACE) => Ok(Stmt::Block(self.block()?)),
+            Some(SEMI) => Ok(Stmt::Empty(self.bump())),
+            Some(TokenType::Keyword("if")) => {
+                let keyword = self.bump();
+                let cond = self.condition()?;
+                let then = Box::new(self.statement()?);
+                let otherwise = match self.eat(TokenType::Keyword("else")) {
+                    Some(keyword) => Some((keyword, Box::new(self.statement()?))),
+                    None => None,
+                };
+                Ok(Stmt::If {
+                    keyword,
+                    cond,
+                    then,
+                    otherwise,
+                })
+            }
+            Some(TokenType::Keyword("switch")) => {
+                let keyword = self.bump();
+                let cond = self.condition()?;
+                let body = Box::new(self.statement()?);
+                Ok(Stmt::Switch {
+                    keyword,
+                    cond,
+                    body,
+                })
+            }
+            Some(TokenType::Keyword("while")) => {
+                let keyword = self.bump();
+                let cond = self.condition()?;
+                let body = Box::new(self.statement()?);
+                Ok(Stmt::While {
+                    keyword,
+                    cond,
+                    body,
+                })
+            }
+            Some(TokenType::Keyword("do")) => {
+                let do_keyword = self.bump();
+                let body = Box::new(self.statement()?);
+                let while_keyword = self.expect(TokenType::Keyword("while"), "`while`")?;
+                let cond = self.condition()?;
+                let semi = self.expect(SEMI, "`;`")?;
+                Ok(Stmt::DoWhile {
+                    do_keyword,
+                    body,
+                    while_keyword,
+                    cond,
+                    semi,
+                })
+            }
+            Some(TokenType::Keyword("for")) => self.for_statement(),
+            Some(TokenType::Keyword("return" | "break" | "continue" | "goto")) => {
+                let keyword = self.bump();
+                let value = match self.at(SEMI) {
+                    true => None,
+                    false => Some(self.expr()?),
+                };
+                let semi = self.expect(SEMI, "`;`")?;
+                Ok(Stmt::Jump {
+                    keyword,
+                    value,
+                    semi,
+                })
+            }
+            Some(TokenType::Keyword("case")) => {
+                let keyword = self.bump();
+                let mut value = self.conditional()?;
+                // the GNU case range `case 'a' ... 'z':`
+                if let Some(op) = self.eat(TokenType::Punctuation(PunctType::Ellipsis)) {
+                    let rhs = self.conditional()?;
+                    value = Expr::Binary {
+                        lhs: Box::new(value),
+                        op,
+                        rhs: Box::new(rhs),
+                    };
+                }
+                let colon = self.expect(COLON, "`:`")?;
+                Ok(Stmt::Case {
+                    keyword,
+                    value,
+                    colon,
+                })
+            }
+            Some(TokenType::Keyword("default")) => {
+                let keyword = self.bump();
+                let colon = self.expect(COLON, "`:`")?;
+                Ok(Stmt::Default { keyword, colon })
+            }
+            Some(TokenType::Indentifier(name))
+                if self.macros.is_for_each(name) && self.nth(1) == Some(LPAREN) =>
+            {
+                let name = self.bump();
+                let args = self.group()?;
+                let body = Box::new(self.statement()?);
+                Ok(Stmt::ForEach { name, args, body })
+            }
+            _ if self.at_statement_macro() => Ok(Stmt::Macro(self.macro_call()?)),
+            _ if self.at_static_assert() => Ok(Stmt::StaticAssert(self.static_assert()?)),
+            Some(TokenType::Indentifier(_)) if self.nth(1) == Some(COLON) => {
+                let name = self.bump();
+                let colon = self.bump();
+                Ok(Stmt::Label { name, colon })
+            }
+            _ if self.at_declaration() => Ok(Stmt::Declaration(self.declaration()?)),
+            _ => {
+                let expr = self.expr()?;
+                let semi = self.expect(SEMI, "`;`")?;
+                Ok(Stmt::Expr { expr, semi })
+            }
+        }
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.bump();
+        let open = self.expect(LPAREN, "`(`")?;
+        let init = if self.at_declaration() {
+            ForInit::Declaration(self.declaration()?)
+        } else {
+            let expr = match self.at(SEMI) {
+                true => None,
+                false => Some(self.expr()?),
+            };
+            ForInit::Expr(expr, self.expect(SEMI, "`;`")?)
+        };
+        let cond = match self.at(SEMI) {
+            true => None,
+            false => Some(self.expr()?),
+        };
+        let cond_semi = self.expect(SEMI, "`;`")?;
+        let step = match self.at(RPAREN) {
+            true => None,
+            false => Some(self.expr()?),
+        };
+        let close = self.expect(RPAREN, "`)`")?;
+        let body = Box::new(self.statement()?);
+        Ok(Stmt::For {
+            keyword,
+            open,
+            init,
+            cond,
+            cond_semi,
+            step,
+            close,
+            body,
+        })
+    }
+
+    fn condition(&mut self) -> Result<Condition> {
+        let open = self.expect(LPAREN, "`(`")?;
+        let expr = self.expr()?;
+        let close = self.expect(RPAREN, "`)`")?;
+        Ok(Condition { open, expr, close })
+    }
+}
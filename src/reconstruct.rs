@@ -1,10 +1,37 @@
 use std::borrow::Cow;
 
-use crate::{Token, TokenType, LINE_ENDING};
+use crate::{Indentation, Token, LINE_ENDING};
 
+#[cfg(test)]
 pub fn reconstruct<'a, I>(tokens: I) -> String
 where
     I: Iterator<Item = Token<'a>>,
+{
+    write(tokens, |col, _| " ".repeat(col))
+}
+
+/// like [`reconstruct`], with the whitespace at the start of each line made according to
+/// `indentation`. `levels` are the columns of block indentation of each line
+pub(crate) fn reconstruct_indented<'a, I>(
+    tokens: I,
+    levels: &[usize],
+    indentation: &Indentation,
+) -> String
+where
+    I: Iterator<Item = Token<'a>>,
+{
+    write(tokens, |col, line| {
+        let level = levels.get(line).copied().unwrap_or_default();
+        indentation.whitespace(col, level)
+    })
+}
+
+/// writes the tokens at their locations, `leading` gives the whitespace before the first token
+/// on a line from its column and line
+fn write<'a, I, F>(tokens: I, leading: F) -> String
+where
+    I: Iterator<Item = Token<'a>>,
+    F: Fn(usize, usize) -> String,
 {
     let mut out = String::new();
     let mut col = 0;
@@ -16,18 +43,25 @@ where
             out.push_str(LINE_ENDING.repeat(token.location.line - line).as_str());
             col = 0;
         }
-        if col < token.location.col {
+        if col == 0 && token.location.col > 0 {
+            out.push_str(&leading(token.location.col, token.location.line));
+        } else if col < token.location.col {
             out.push_str(" ".repeat(token.location.col - col).as_str());
         }
 
-        col = token.location.col + token.token_type.width();
-        line = token.location.line;
-        if matches!(token.token_type, TokenType::Linebreak) {
-            line += 1;
-            col = 0;
+        let text = token.token_type.to_str();
+        match text.rsplit_once(LINE_ENDING) {
+            Some((head, tail)) => {
+                line = token.location.line + head.matches(LINE_ENDING).count() + 1;
+                col = tail.chars().count();
+            }
+            None => {
+                line = token.location.line;
+                col = token.location.col + token.token_type.width();
+            }
         }
 
-        match token.token_type.to_str() {
+        match text {
             Cow::Borrowed(s) => out.push_str(s),
             Cow::Owned(s) => out.push_str(s.as_str()),
         }
@@ -0,0 +1,202 @@
+use std::{iter::Peekable, str::Chars};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Char(char),
+    Any,
+    /// the ranges of characters in a class, and whether it is negated
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    Alternatives(Vec<Vec<Node>>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+/// a regular expression from the configuration, matched by backtracking
+///
+/// supports literals, `.`, `^`, `$`, classes like `[a-z_]` and `[^/]`, groups, alternatives
+/// with `|`, the repetitions `*`, `+` and `?`, and `\` escaping the character after it
+#[derive(Debug, Clone)]
+pub(crate) struct Regex {
+    alternatives: Vec<Vec<Node>>,
+}
+
+impl Regex {
+    /// compiles `pattern`, or returns `None` if it is not valid
+    pub(crate) fn new(pattern: &str) -> Option<Self> {
+        let mut chars = pattern.chars().peekable();
+        let alternatives = alternatives(&mut chars)?;
+        match chars.next() {
+            Some(_) => None,
+            None => Some(Self { alternatives }),
+        }
+    }
+
+    /// whether the pattern matches somewhere in `text`
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let pattern = [Node::Alternatives(self.alternatives.clone())];
+        (0..=text.len()).any(|start| matches(&pattern, &text, start, &mut |_| true))
+    }
+}
+
+fn alternatives(chars: &mut Peekable<Chars>) -> Option<Vec<Vec<Node>>> {
+    let mut alternatives = vec![sequence(chars)?];
+    while chars.next_if_eq(&'|').is_some() {
+        alternatives.push(sequence(chars)?);
+    }
+    Some(alternatives)
+}
+
+fn sequence(chars: &mut Peekable<Chars>) -> Option<Vec<Node>> {
+    let mut nodes = vec![];
+    while let Some(&ch) = chars.peek() {
+        if matches!(ch, '|' | ')') {
+            break;
+        }
+        chars.next();
+        let node = match ch {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => Node::Char(chars.next()?),
+            '(' => {
+                let alternatives = alternatives(chars)?;
+                chars.next_if_eq(&')')?;
+                Node::Alternatives(alternatives)
+            }
+            '[' => class(chars)?,
+            '*' | '+' | '?' => {
+                let node = nodes.pop()?;
+                let (min, max) = match ch {
+                    '*' => (0, None),
+                    '+' => (1, None),
+                    _ => (0, Some(1)),
+                };
+                Node::Repeat(Box::new(node), min, max)
+            }
+            ch => Node::Char(ch),
+        };
+        nodes.push(node);
+    }
+    Some(nodes)
+}
+
+/// the rest of a class after its `[`
+fn class(chars: &mut Peekable<Chars>) -> Option<Node> {
+    let negated = chars.next_if_eq(&'^').is_some();
+    let mut ranges = vec![];
+    loop {
+        let first = match chars.next()? {
+            ']' if !ranges.is_empty() => return Some(Node::Class(ranges, negated)),
+            '\\' => chars.next()?,
+            ch => ch,
+        };
+        let mut peek = chars.clone();
+        let last = match (peek.next(), peek.next()) {
+            (Some('-'), Some(last)) if last != ']' => {
+                chars.next();
+                chars.next();
+                last
+            }
+            _ => first,
+        };
+        ranges.push((first, last));
+    }
+}
+
+/// whether `nodes` match `text` from `pos`, followed by whatever `rest` accepts of the position
+/// where the match ends
+fn matches(nodes: &[Node], text: &[char], pos: usize, rest: &mut dyn FnMut(usize) -> bool) -> bool {
+    let Some((node, nodes)) = nodes.split_first() else {
+        return rest(pos);
+    };
+    let single = |accept: bool| accept.then_some(pos + 1);
+    let next = match node {
+        Node::Char(ch) => single(text.get(pos) == Some(ch)),
+        Node::Any => single(pos < text.len()),
+        Node::Class(ranges, negated) => single(text.get(pos).is_some_and(|ch| {
+            ranges
+                .iter()
+                .any(|(first, last)| (first..=last).contains(&ch))
+                != *negated
+        })),
+        Node::Start => (pos == 0).then_some(pos),
+        Node::End => (pos == text.len()).then_some(pos),
+        Node::Alternatives(alternatives) => {
+            return alternatives.iter().any(|alternative| {
+                matches(alternative, text, pos, &mut |end| {
+                    matches(nodes, text, end, rest)
+                })
+            });
+        }
+        Node::Repeat(node, min, max) => return repeat(node, *min, *max, nodes, text, pos, rest),
+    };
+    next.is_some_and(|next| matches(nodes, text, next, rest))
+}
+
+/// matches `node` as many times as possible, at least `min` and at most `max` times, followed
+/// by `nodes`
+fn repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    nodes: &[Node],
+    text: &[char],
+    pos: usize,
+    rest: &mut dyn FnMut(usize) -> bool,
+) -> bool {
+    if max != Some(0) {
+        let once = std::slice::from_ref(node);
+        let more = matches(once, text, pos, &mut |end| {
+            // a repetition of an empty match would not end
+            end > pos
+                && repeat(
+                    node,
+                    min.saturating_sub(1),
+                    max.map(|max| max - 1),
+                    nodes,
+                    text,
+                    end,
+                    rest,
+                )
+        });
+        if more {
+            return true;
+        }
+    }
+    min == 0 && matches(nodes, text, pos, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(is_match("std", "<stdio.h>"));
+        assert!(is_match(r"^<[a-z]+\.h>$", "<stdio.h>"));
+        assert!(!is_match(r"^<[a-z]+\.h>$", "<sys/types.h>"));
+        assert!(is_match(r"^<(sys/)?[a-z_]+\.h>$", "<sys/types.h>"));
+        assert!(is_match(r#"^"(foo|bar)/"#, "\"bar/baz.h\""));
+        assert!(!is_match(r#"^"(foo|bar)/"#, "\"baz/bar/x.h\""));
+        assert!(is_match("^<[^/]*>$", "<vector>"));
+        assert!(!is_match("^<[^/]*>$", "<gtest/gtest.h>"));
+        assert!(is_match("a.*b.*c", "xaxxbxxcx"));
+        assert!(is_match("^(a|ab)c$", "abc"));
+        assert!(is_match("^x?y+$", "yyy"));
+        assert!(is_match(".*", ""));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(Regex::new("(a").is_none());
+        assert!(Regex::new("a)").is_none());
+        assert!(Regex::new("*a").is_none());
+        assert!(Regex::new("[a").is_none());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    ast::{FunctionDef, Item, TokenIdx, TranslationUnit},
+    Location, Token, TokenType,
+};
+
+/// rewrites old-style function definitions to prototype style by moving the declaration of each
+/// parameter into the parameter list
+///
+/// a definition is left as it is unless every parameter is declared exactly once, nothing else is
+/// declared and no comments sit between the parameter list and the body
+pub(crate) fn old_style_definitions<'a>(
+    tokens: &[Token<'a>],
+    unit: &TranslationUnit,
+) -> Vec<Token<'a>> {
+    let mut tokens = tokens.to_vec();
+    // from the last definition to the first, so the indices of the earlier ones stay valid
+    for item in unit.items.iter().rev() {
+        let Item::Function(function) = item else {
+            continue;
+        };
+        if let Some(params) = prototype_params(&tokens, function) {
+            rewrite(&mut tokens, function, params);
+        }
+    }
+    tokens
+}
+
+fn name<'a>(tokens: &[Token<'a>], idx: TokenIdx) -> Option<&'a str> {
+    match tokens[idx].token_type {
+        TokenType::Indentifier(name) => Some(name),
+        _ => None,
+    }
+}
+
+fn significant(tokens: &[Token], range: std::ops::Range<TokenIdx>) -> Vec<TokenIdx> {
+    range
+        .filter(|&idx| !tokens[idx].token_type.is_trivia())
+        .collect()
+}
+
+/// the tokens of each parameter in prototype style, if the definition can be rewritten
+fn prototype_params(tokens: &[Token], function: &FunctionDef) -> Option<Vec<Vec<TokenIdx>>> {
+    let list = function.declarator.params()?;
+    if function.param_declarations.is_empty() || !function.attributes.is_empty() {
+        return None;
+    }
+    let has_comments = tokens[list.open..function.body.open]
+        .iter()
+        .any(|token| token.token_type.is_trivia() && token.token_type != TokenType::Linebreak);
+    if has_comments {
+        return None;
+    }
+
+    let names = list
+        .params
+        .iter()
+        .map(|param| name(tokens, param.name_only()?))
+        .collect::<Option<Vec<_>>>()?;
+    let mut params = vec![None; names.len()];
+
+    let mut start = list.close + 1;
+    for declaration in &function.param_declarations {
+        let first = declaration.declarators.first()?.declarator.first_token()?;
+        let specifiers = significant(tokens, start..first);
+        if specifiers.is_empty() {
+            return None;
+        }
+        for (i, declarator) in declaration.declarators.iter().enumerate() {
+            if declarator.init.is_some() || declarator.bitfield.is_some() {
+                return None;
+            }
+            let declared = name(tokens, declarator.declarator.name()?)?;
+            let param = names.iter().position(|name| *name == declared)?;
+            if params[param].is_some() {
+                return None;
+            }
+            let end = declaration
+                .commas
+                .get(i)
+                .copied()
+                .unwrap_or(declaration.semi);
+            let mut tokens_of_param = specifiers.clone();
+            tokens_of_param.extend(significant(
+                tokens,
+                declarator.declarator.first_token()?..end,
+            ));
+            params[param] = Some(tokens_of_param);
+        }
+        start = declaration.semi + 1;
+    }
+
+    // parameters without a declaration are `int`, which is left for the reader to see
+    params.into_iter().collect()
+}
+
+/// appends `token` to `header` at `col`, after a space if `space`
+fn place<'a>(
+    header: &mut Vec<Token<'a>>,
+    token: Token<'a>,
+    space: bool,
+    line: usize,
+    col: &mut usize,
+) {
+    if space {
+        *col += 1;
+    }
+    header.push(Token::with_offset(
+        token.token_type,
+        Location::new(line, *col),
+        token.offset,
+    ));
+    *col += token.token_type.width();
+}
+
+fn rewrite(tokens: &mut Vec<Token>, function: &FunctionDef, params: Vec<Vec<TokenIdx>>) {
+    let list = function
+        .declarator
+        .params()
+        .expect("only definitions with parameters are rewritten");
+    let brace = function.body.open;
+    let open = tokens[list.open].location;
+    let line = open.line;
+    let mut col = open.col + tokens[list.open].token_type.width();
+
+    let mut header = vec![];
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            place(
+                &mut header,
+                tokens[list.commas[i - 1]],
+                false,
+                line,
+                &mut col,
+            );
+        }
+        for (j, &idx) in param.iter().enumerate() {
+            // keep the tokens of a parameter apart where they were apart in the source
+            let space = match j {
+                0 => i > 0,
+                _ => {
+                    let previous = &tokens[param[j - 1]];
+                    previous.offset + previous.token_type.len() != tokens[idx].offset
+                }
+            };
+            place(&mut header, tokens[idx], space, line, &mut col);
+        }
+    }
+    place(&mut header, tokens[list.close], false, line, &mut col);
+
+    let old = tokens[brace].location;
+    let new = match tokens[brace - 1].token_type {
+        TokenType::Linebreak => {
+            place(&mut header, tokens[brace - 1], false, line, &mut col);
+            Location::new(line + 1, old.col)
+        }
+        _ => Location::new(line, col + 1),
+    };
+    for token in &mut tokens[brace..] {
+        if token.location.line == old.line {
+            token.location.col = token.location.col - old.col + new.col;
+        }
+        token.location.line = token.location.line - old.line + new.line;
+    }
+
+    tokens.splice(list.open + 1..brace, header);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config};
+
+    fn convert(program: &str) -> String {
+        let config = Config {
+            convert_old_style_definitions: true,
+            ..Config::default()
+        };
+        format_with_config(program, &config).output
+    }
+
+    #[test]
+    fn brace_on_its_own_line() {
+        let program = "int f(a, b)\nint a;\nchar *b;\n{\n    return a;\n}\n";
+        assert_eq!(
+            convert(program),
+            "int f(int a, char *b)\n{\n    return a;\n}\n"
+        );
+    }
+
+    #[test]
+    fn shared_specifiers() {
+        let program = "static long f(n, s, c) char *s, c; long n; { return n; }\nint g;\n";
+        assert_eq!(
+            convert(program),
+            "static long f(long n, char *s, char c)\n{\n    return n;\n}\nint g;\n"
+        );
+    }
+
+    #[test]
+    fn ambiguous_definitions_are_kept() {
+        let program =
+            "int f(a, b)\nint a;\n{\n    return a;\n}\nint g(a)\nint a; /* count */\n{\n}\n";
+        assert_eq!(convert(program), program);
+    }
+}
@@ -87,6 +87,20 @@ pub enum OpType {
     Eq,
     /// the operator `!=`
     Neq,
+    /// the operator `<`
+    Lt,
+    /// the operator `>`
+    Gt,
+    /// the operator `<=`
+    Le,
+    /// the operator `>=`
+    Ge,
+
+    // increment / decrement
+    /// the operator `++`
+    Inc,
+    /// the operator `--`
+    Dec,
 }
 
 impl FromStr for OpType {
@@ -124,6 +138,12 @@ impl FromStr for OpType {
             "!" => Not,
             "==" => Eq,
             "!=" => Neq,
+            "<" => Lt,
+            ">" => Gt,
+            "<=" => Le,
+            ">=" => Ge,
+            "++" => Inc,
+            "--" => Dec,
             _ => return Err(ParseError),
         })
     }
@@ -162,6 +182,12 @@ impl OpType {
             Not => "!",
             Eq => "==",
             Neq => "!=",
+            Lt => "<",
+            Gt => ">",
+            Le => "<=",
+            Ge => ">=",
+            Inc => "++",
+            Dec => "--",
         }
     }
 }
@@ -181,6 +207,10 @@ pub enum PunctType {
     QuestionMark,
     /// the punctuation :
     Colon,
+    /// the punctuation ...
+    Ellipsis,
+    /// the punctuation # that starts a preprocessor directive
+    Hash,
 }
 
 impl PunctType {
@@ -192,6 +222,8 @@ impl PunctType {
             PunctType::Semicolon => ";",
             PunctType::QuestionMark => "?",
             PunctType::Colon => ":",
+            PunctType::Ellipsis => "...",
+            PunctType::Hash => "#",
         }
     }
 }
@@ -213,8 +245,21 @@ pub enum ParenType {
     RBrack,
 }
 
+impl ParenType {
+    fn to_str(self) -> &'static str {
+        match self {
+            ParenType::LParen => "(",
+            ParenType::RParen => ")",
+            ParenType::LBrace => "{",
+            ParenType::RBrace => "}",
+            ParenType::LBrack => "[",
+            ParenType::RBrack => "]",
+        }
+    }
+}
+
 /// Type of a token
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenType<'a> {
     Keyword(&'a str),
     Operator(OpType),
@@ -223,9 +268,22 @@ pub enum TokenType<'a> {
     /// any other literal value, characters are including the quotes
     Const(&'a str),
     Punctuation(PunctType),
+    Paren(ParenType),
     Indentifier(&'a str),
     Linebreak,
+    /// a `//` comment, without the slashes
     Comment(&'a str),
+    /// a `/* */` comment, without the delimiters. may span several lines
+    BlockComment(&'a str),
+    /// the text of one line of a preprocessor directive, following the `#` on the first line
+    Directive(&'a str),
+    /// a `\` that joins a line with the next one
+    LineContinuation,
+    /// text that cannot appear in C outside of literals, like `@` or an unterminated comment.
+    /// may span several lines
+    Unknown(&'a str),
+    /// source text that is passed through unchanged. may span several lines
+    Verbatim(&'a str),
 }
 
 impl<'a> TokenType<'a> {
@@ -241,23 +299,26 @@ impl<'a> TokenType<'a> {
             TokenType::Operator(op) => strlen!(op.to_str()),
             TokenType::Str(lit) => strlen!(lit),
             TokenType::Const(cons) => strlen!(cons),
-            TokenType::Punctuation(pt) => match pt {
-                PunctType::Comma
-                | PunctType::Dot
-                | PunctType::Semicolon
-                | PunctType::QuestionMark
-                | PunctType::Colon => 1,
-                PunctType::Arrow => 2,
-            },
+            TokenType::Punctuation(pt) => strlen!(pt.to_str()),
+            TokenType::Paren(_) => 1,
             TokenType::Indentifier(ident) => strlen!(ident),
             TokenType::Comment(com) => strlen!(com) + 2,
+            TokenType::BlockComment(com) => strlen!(com) + 4,
+            TokenType::Directive(text) => strlen!(text),
+            TokenType::LineContinuation => 1,
+            TokenType::Unknown(text) | TokenType::Verbatim(text) => strlen!(text),
             TokenType::Linebreak => strlen!(LINE_ENDING),
         }
     }
 
     /// returns the number of rows this token spans
-    pub const fn height(&self) -> usize {
-        1
+    pub fn height(&self) -> usize {
+        match self {
+            TokenType::BlockComment(text)
+            | TokenType::Unknown(text)
+            | TokenType::Verbatim(text) => text.matches(LINE_ENDING).count() + 1,
+            _ => 1,
+        }
     }
 
     /// returns the length of the source text represented by the token in bytes
@@ -267,16 +328,14 @@ impl<'a> TokenType<'a> {
             TokenType::Operator(op) => op.to_str().len(),
             TokenType::Str(lit) => lit.len(),
             TokenType::Const(cons) => cons.len(),
-            TokenType::Punctuation(pt) => match pt {
-                PunctType::Comma
-                | PunctType::Dot
-                | PunctType::Semicolon
-                | PunctType::QuestionMark
-                | PunctType::Colon => 1,
-                PunctType::Arrow => 2,
-            },
+            TokenType::Punctuation(pt) => pt.to_str().len(),
+            TokenType::Paren(_) => 1,
             TokenType::Indentifier(ident) => ident.len(),
             TokenType::Comment(com) => com.len() + 2,
+            TokenType::BlockComment(com) => com.len() + 4,
+            TokenType::Directive(text) => text.len(),
+            TokenType::Unknown(text) | TokenType::Verbatim(text) => text.len(),
+            TokenType::LineContinuation => 1,
             TokenType::Linebreak => LINE_ENDING.len(),
         }
     }
@@ -288,18 +347,36 @@ impl<'a> TokenType<'a> {
             TokenType::Str(s) => Cow::Borrowed(s),
             TokenType::Const(con) => Cow::Borrowed(con),
             TokenType::Punctuation(pt) => Cow::Borrowed(pt.to_str()),
+            TokenType::Paren(paren) => Cow::Borrowed(paren.to_str()),
             TokenType::Indentifier(ident) => Cow::Borrowed(ident),
             TokenType::Linebreak => Cow::Borrowed(LINE_ENDING),
             TokenType::Comment(com) => Cow::Owned(format!("//{com}")),
+            TokenType::BlockComment(com) => Cow::Owned(format!("/*{com}*/")),
+            TokenType::Directive(text) => Cow::Borrowed(text),
+            TokenType::LineContinuation => Cow::Borrowed("\\"),
+            TokenType::Unknown(text) | TokenType::Verbatim(text) => Cow::Borrowed(text),
         }
     }
+
+    /// whether the token is only relevant for the layout and not for the syntax
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenType::Linebreak
+                | TokenType::Comment(_)
+                | TokenType::BlockComment(_)
+                | TokenType::LineContinuation
+        )
+    }
 }
 
 /// a token and its location
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Token<'a> {
     pub(crate) token_type: TokenType<'a>,
     pub(crate) location: Location,
+    /// the byte offset of the token in the source it was read from
+    pub(crate) offset: usize,
 }
 
 impl<'a> Token<'a> {
@@ -307,6 +384,19 @@ impl<'a> Token<'a> {
         Self {
             token_type,
             location,
+            offset: 0,
+        }
+    }
+
+    pub(crate) fn with_offset(
+        token_type: TokenType<'a>,
+        location: Location,
+        offset: usize,
+    ) -> Self {
+        Self {
+            token_type,
+            location,
+            offset,
         }
     }
 }
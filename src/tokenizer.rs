@@ -1,10 +1,148 @@
-use crate::{Location, OpType, PunctType, Token, TokenType, LINE_ENDING};
+use crate::{Location, OpType, ParenType, PunctType, Token, TokenType, LINE_ENDING};
+
+/// keywords of the C language, including the common GNU spellings
+const KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Alignas",
+    "_Alignof",
+    "_Atomic",
+    "_Bool",
+    "_Complex",
+    "_Generic",
+    "_Imaginary",
+    "_Noreturn",
+    "_Static_assert",
+    "_Thread_local",
+    "alignas",
+    "alignof",
+    "bool",
+    "constexpr",
+    "false",
+    "nullptr",
+    "static_assert",
+    "thread_local",
+    "true",
+    "typeof_unqual",
+    "_BitInt",
+    "_Decimal32",
+    "_Decimal64",
+    "_Decimal128",
+    "__attribute__",
+    "__attribute",
+    "asm",
+    "__asm__",
+    "__asm",
+    "__inline__",
+    "__inline",
+    "__restrict__",
+    "__restrict",
+    "__volatile__",
+    "__extension__",
+    "__typeof__",
+    "__typeof",
+    "typeof",
+    "__const",
+    "__signed__",
+    "__alignof__",
+    "__thread",
+];
+
+/// operators and punctuation, longest first so that the first match is the right one
+const OPERATORS: &[(&str, TokenType<'static>)] = &[
+    ("...", TokenType::Punctuation(PunctType::Ellipsis)),
+    ("<<=", TokenType::Operator(OpType::ShlAssign)),
+    (">>=", TokenType::Operator(OpType::ShrAssign)),
+    ("->", TokenType::Punctuation(PunctType::Arrow)),
+    ("++", TokenType::Operator(OpType::Inc)),
+    ("--", TokenType::Operator(OpType::Dec)),
+    ("<<", TokenType::Operator(OpType::Shl)),
+    (">>", TokenType::Operator(OpType::Shr)),
+    ("<=", TokenType::Operator(OpType::Le)),
+    (">=", TokenType::Operator(OpType::Ge)),
+    ("==", TokenType::Operator(OpType::Eq)),
+    ("!=", TokenType::Operator(OpType::Neq)),
+    ("&&", TokenType::Operator(OpType::And)),
+    ("||", TokenType::Operator(OpType::Or)),
+    ("+=", TokenType::Operator(OpType::PlusAssign)),
+    ("-=", TokenType::Operator(OpType::SubAssign)),
+    ("*=", TokenType::Operator(OpType::MulAssign)),
+    ("/=", TokenType::Operator(OpType::DivAssign)),
+    ("%=", TokenType::Operator(OpType::ModAssign)),
+    ("&=", TokenType::Operator(OpType::LAndAssign)),
+    ("|=", TokenType::Operator(OpType::LOrAssign)),
+    ("^=", TokenType::Operator(OpType::LXorAssign)),
+    ("=", TokenType::Operator(OpType::Assign)),
+    ("+", TokenType::Operator(OpType::Plus)),
+    ("-", TokenType::Operator(OpType::Sub)),
+    ("*", TokenType::Operator(OpType::Mul)),
+    ("/", TokenType::Operator(OpType::Div)),
+    ("%", TokenType::Operator(OpType::Mod)),
+    ("<", TokenType::Operator(OpType::Lt)),
+    (">", TokenType::Operator(OpType::Gt)),
+    ("!", TokenType::Operator(OpType::Not)),
+    ("~", TokenType::Operator(OpType::LNot)),
+    ("&", TokenType::Operator(OpType::LAnd)),
+    ("|", TokenType::Operator(OpType::LOr)),
+    ("^", TokenType::Operator(OpType::LXor)),
+    ("?", TokenType::Punctuation(PunctType::QuestionMark)),
+    (":", TokenType::Punctuation(PunctType::Colon)),
+    (";", TokenType::Punctuation(PunctType::Semicolon)),
+    (",", TokenType::Punctuation(PunctType::Comma)),
+    (".", TokenType::Punctuation(PunctType::Dot)),
+    ("(", TokenType::Paren(ParenType::LParen)),
+    (")", TokenType::Paren(ParenType::RParen)),
+    ("{", TokenType::Paren(ParenType::LBrace)),
+    ("}", TokenType::Paren(ParenType::RBrace)),
+    ("[", TokenType::Paren(ParenType::LBrack)),
+    ("]", TokenType::Paren(ParenType::RBrack)),
+];
 
 #[derive(Debug)]
 pub struct Tokenizer<'a> {
     data: &'a str,
     col: usize,
     row: usize,
+    /// the byte offset of `data` in the source
+    offset: usize,
+    /// whether only whitespace has been seen on the current line
+    line_start: bool,
+    /// whether the tokenizer is inside of a preprocessor directive
+    directive: bool,
+    /// whether the next line break was escaped with a `\`
+    continued: bool,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -13,65 +151,121 @@ impl<'a> Tokenizer<'a> {
             data,
             col: 0,
             row: 0,
+            offset: 0,
+            line_start: true,
+            directive: false,
+            continued: false,
         }
     }
 }
 
 impl<'a> Tokenizer<'a> {
-    fn delimiter_single_char<'b: 'a, 'c: 'a>(
-        &mut self,
-        delimiter: char,
-        corresponds: TokenType<'b>,
-        before: &'c str,
-    ) -> Token<'a> {
-        let (row, col) = (self.row, self.col);
-        let loc = Location::new(row, col);
-
-        if before.is_empty() {
-            self.col += delimiter.len_utf8();
-            self.data = &self.data[delimiter.len_utf8()..];
-            Token::new(corresponds, loc)
-        } else {
-            self.data = &self.data[before.len()..];
-            self.col += before.chars().count();
-            // fixme, this shouldnt be an ident but rather something that is decided depending on
-            // the content of the `before` variable
-            Token::new(TokenType::Indentifier(before), loc)
+    /// consumes the next `len` bytes of the input as a token of the given type
+    fn take(&mut self, len: usize, token_type: TokenType<'a>) -> Token<'a> {
+        let token = Token::with_offset(token_type, Location::new(self.row, self.col), self.offset);
+
+        let text = &self.data[..len];
+        match text.rsplit_once(LINE_ENDING) {
+            Some((head, tail)) => {
+                self.row += head.matches(LINE_ENDING).count() + 1;
+                self.col = tail.chars().count();
+            }
+            None => self.col += text.chars().count(),
         }
+        self.data = &self.data[len..];
+        self.offset += len;
+        self.line_start = false;
+
+        token
     }
 
-    // two chars and not in general because dont need it
-    fn delimiter_two_chars<'b, 'c, F>(
-        &mut self,
-        ch: char,
-        delimiter: F,
-        before: &'c str,
-    ) -> Token<'a>
-    where
-        'c: 'a,
-        'b: 'a,
-        F: Fn(char) -> TokenType<'b>,
-    {
-        let loc = Location::new(self.row, self.col);
-
-        let mut iter = self.data[before.len() + ch.len_utf8()..].chars();
-        let ch_after = iter.next();
-        let corresponds = delimiter(ch_after.unwrap_or('\0')); // just dont have a string
-                                                               // with a NUL character in it what could go wrong
-        let len = corresponds.width();
-
-        if before.is_empty() {
-            self.col += len;
-            self.data = &self.data[corresponds.len()..];
-            Token::new(corresponds, loc)
-        } else {
-            self.data = &self.data[before.len()..];
-            self.col += before.chars().count();
-            // fixme, this shouldnt be an ident but rather something that is decided depending on
-            // the content of the `before` variable
-            Token::new(TokenType::Indentifier(before), loc)
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.data.chars().next() {
+            if !matches!(ch, ' ' | '\t' | '\r' | '\x0b' | '\x0c')
+                || self.data.starts_with(LINE_ENDING)
+            {
+                break;
+            }
+            self.col += 1;
+            self.offset += ch.len_utf8();
+            self.data = &self.data[ch.len_utf8()..];
+        }
+    }
+
+    /// reads the text of a directive up to the end of the line, a comment or a line continuation
+    fn directive_text(&mut self) -> Token<'a> {
+        let data = self.data;
+        let mut end = 0;
+        let mut i = 0;
+        while let Some(ch) = data[i..].chars().next() {
+            let rest = &data[i..];
+            if rest.starts_with(LINE_ENDING)
+                || rest.starts_with("//")
+                || rest.starts_with("/*")
+                || (ch == '\\' && is_continuation(&rest[1..]))
+            {
+                break;
+            }
+            if let Some(len) = matches!(ch, '"' | '\'').then(|| quoted_len(rest)).flatten() {
+                i += len;
+                end = i;
+                continue;
+            }
+            i += ch.len_utf8();
+            if !ch.is_whitespace() {
+                end = i;
+            }
+        }
+        self.take(end, TokenType::Directive(&data[..end]))
+    }
+}
+
+/// whether a `\` followed by `rest` escapes a line break
+fn is_continuation(rest: &str) -> bool {
+    handle_newline(rest.trim_start_matches([' ', '\t', '\r']), LINE_ENDING).is_some()
+}
+
+/// the length of the character or string literal at the start of `data`, if it is terminated on
+/// the same line
+fn quoted_len(data: &str) -> Option<usize> {
+    let quote = data.chars().next()?;
+    let mut iter = data.char_indices().skip(1);
+    while let Some((i, ch)) = iter.next() {
+        if data[i..].starts_with(LINE_ENDING) {
+            return None;
+        }
+        match ch {
+            '\\' => {
+                iter.next();
+            }
+            ch if ch == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// the length of the character or string literal at the start of `data`, running until the end
+/// of the line if it is not terminated
+fn literal_len(data: &str) -> usize {
+    quoted_len(data).unwrap_or_else(|| data.find(LINE_ENDING).unwrap_or(data.len()))
+}
+
+/// the length of the preprocessing number at the start of `data`
+fn number_len(data: &str) -> usize {
+    let mut prev = '\0';
+    for (i, ch) in data.char_indices() {
+        let sign = matches!(ch, '+' | '-') && matches!(prev, 'e' | 'E' | 'p' | 'P');
+        if !(ch.is_alphanumeric() || matches!(ch, '_' | '.') || sign) {
+            return i;
         }
+        prev = ch;
     }
+    data.len()
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '$')
 }
 
 fn handle_newline<'a>(data: &'a str, nl: &'static str) -> Option<&'a str> {
@@ -82,115 +276,89 @@ impl<'a> Iterator for Tokenizer<'a> {
     type Item = Token<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut iter = self.data.char_indices();
-        while let Some((i, ch)) = iter.next() {
-            let before = &self.data[..i];
-            if let Some(data) = handle_newline(self.data, LINE_ENDING) {
-                self.data = data;
-                let loc = Location::new(self.row, self.col);
-                self.row += 1;
-                self.col = 0;
-                return Some(Token {
-                    token_type: TokenType::Linebreak,
-                    location: loc,
+        self.skip_whitespace();
+        let data = self.data;
+        let ch = data.chars().next()?;
+
+        if handle_newline(data, LINE_ENDING).is_some() {
+            let token = self.take(LINE_ENDING.len(), TokenType::Linebreak);
+            self.line_start = !self.continued;
+            self.directive &= self.continued;
+            self.continued = false;
+            return Some(token);
+        }
+        if ch == '\\' && is_continuation(&data[1..]) {
+            self.continued = true;
+            return Some(self.take(1, TokenType::LineContinuation));
+        }
+        if data.starts_with("//") {
+            let len = data.find(LINE_ENDING).unwrap_or(data.len());
+            let text = data[2..len].trim_end_matches('\r');
+            return Some(self.take(text.len() + 2, TokenType::Comment(text)));
+        }
+        if let Some(rest) = data.strip_prefix("/*") {
+            return Some(match rest.find("*/") {
+                Some(end) => self.take(end + 4, TokenType::BlockComment(&rest[..end])),
+                // an unterminated comment reaches to the end of the input and is kept as it is
+                None => self.take(data.len(), TokenType::Unknown(data)),
+            });
+        }
+        if self.directive {
+            return Some(self.directive_text());
+        }
+        if ch == '#' && self.line_start {
+            self.directive = true;
+            return Some(self.take(1, TokenType::Punctuation(PunctType::Hash)));
+        }
+
+        if is_ident_char(ch) && !ch.is_ascii_digit() {
+            let len = data.find(|c| !is_ident_char(c)).unwrap_or(data.len());
+            let ident = &data[..len];
+            let rest = &data[len..];
+            if matches!(ident, "L" | "u" | "U" | "u8") && rest.starts_with(['"', '\'']) {
+                let len = len + literal_len(rest);
+                let literal = &data[..len];
+                return Some(if rest.starts_with('"') {
+                    self.take(len, TokenType::Str(literal))
+                } else {
+                    self.take(len, TokenType::Const(literal))
                 });
             }
-
-            match ch {
-                ';' => {
-                    return Some(self.delimiter_single_char(
-                        ';',
-                        TokenType::Punctuation(PunctType::Semicolon),
-                        before,
-                    ))
-                }
-                ',' => {
-                    return Some(self.delimiter_single_char(
-                        ',',
-                        TokenType::Punctuation(PunctType::Comma),
-                        before,
-                    ))
-                }
-                '.' => {
-                    return Some(self.delimiter_single_char(
-                        '.',
-                        TokenType::Punctuation(PunctType::Dot),
-                        before,
-                    ))
-                }
-                '-' => {
-                    return Some(self.delimiter_two_chars(
-                        '-',
-                        |b| match b {
-                            '>' => TokenType::Punctuation(PunctType::Arrow),
-                            '=' => TokenType::Operator(OpType::SubAssign),
-                            _ => TokenType::Operator(OpType::Sub),
-                        },
-                        before,
-                    ))
-                }
-                '+' => {
-                    return Some(self.delimiter_two_chars(
-                        '+',
-                        |b| match b {
-                            '=' => TokenType::Operator(OpType::PlusAssign),
-                            _ => TokenType::Operator(OpType::Plus),
-                        },
-                        before,
-                    ))
-                }
-                '&' => {
-                    return Some(self.delimiter_two_chars(
-                        '&',
-                        |b| match b {
-                            '=' => TokenType::Operator(OpType::LAndAssign),
-                            '&' => TokenType::Operator(OpType::And),
-                            _ => TokenType::Operator(OpType::LAnd),
-                        },
-                        before,
-                    ))
-                }
-
-                ' ' => {
-                    if before.is_empty() {
-                        self.col += 1;
-                        self.data = &self.data[' '.len_utf8()..];
-                        iter = self.data.char_indices();
-                    } else {
-                        let loc = Location::new(self.row, self.col);
-                        self.data = &self.data[before.len() ..];
-                        self.col += before.chars().count();
-                        // fixme, this shouldnt be an ident but rather something that is decided depending on
-                        // the content of the `before` variable
-                        return Some(Token::new(TokenType::Indentifier(before), loc));
-                    }
-
-                }
-
-                'a'..='z' | 'A'..='Z' => {}
-                x => todo!("hit case {x:?}. this is a problem for future me. if you hit this you are future me."),
+            return Some(if KEYWORDS.contains(&ident) {
+                self.take(len, TokenType::Keyword(ident))
+            } else {
+                self.take(len, TokenType::Indentifier(ident))
+            });
+        }
+        if ch.is_ascii_digit() || (ch == '.' && data[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = number_len(data);
+            return Some(self.take(len, TokenType::Const(&data[..len])));
+        }
+        match ch {
+            '"' => {
+                let len = literal_len(data);
+                return Some(self.take(len, TokenType::Str(&data[..len])));
+            }
+            '\'' => {
+                let len = literal_len(data);
+                return Some(self.take(len, TokenType::Const(&data[..len])));
             }
+            _ => {}
         }
 
-        if self.data.is_empty() {
-            None
-        } else {
-            let out = self.data;
-            self.data = "";
-
-            // fixme, this shouldnt be an ident but rather something that is decided depending on
-            // the content of the `before` variable
-            Some(Token::new(
-                TokenType::Indentifier(out),
-                Location::new(self.row, self.col),
-            ))
+        if let Some((op, token_type)) = OPERATORS.iter().find(|(op, _)| data.starts_with(op)) {
+            return Some(self.take(op.len(), *token_type));
         }
+
+        // left for the parser to complain about
+        Some(self.take(ch.len_utf8(), TokenType::Unknown(&data[..ch.len_utf8()])))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{OpType, PunctType, TokenType, Tokenizer};
+    use crate::{reconstruct::reconstruct, OpType, PunctType, TokenType, Tokenizer};
 
     #[test]
     fn only_semicolons() {
@@ -245,4 +413,37 @@ mod tests {
             cur_ind += j.width();
         }
     }
+
+    #[test]
+    fn unknown_characters() {
+        let program = "a @ `b;";
+        let tok: Vec<_> = Tokenizer::new(program).map(|i| i.token_type).collect();
+
+        assert_eq!(
+            tok,
+            vec![
+                TokenType::Indentifier("a"),
+                TokenType::Unknown("@"),
+                TokenType::Unknown("`"),
+                TokenType::Indentifier("b"),
+                TokenType::Punctuation(PunctType::Semicolon),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_comment() {
+        let program = "a; /* comment\nb;";
+        let tok: Vec<_> = Tokenizer::new(program).map(|i| i.token_type).collect();
+
+        assert_eq!(
+            tok,
+            vec![
+                TokenType::Indentifier("a"),
+                TokenType::Punctuation(PunctType::Semicolon),
+                TokenType::Unknown("/* comment\nb;"),
+            ]
+        );
+        assert_eq!(reconstruct(Tokenizer::new(program)), program);
+    }
 }
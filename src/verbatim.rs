@@ -0,0 +1,208 @@
+use crate::{
+    ast::{
+        Block, Declaration, Expr, ForInit, InitEntry, InitList, Item, Stmt, TranslationUnit,
+        Verbatim,
+    },
+    Token, Warning,
+};
+
+/// the verbatim regions of a tree, in the order they appear in the source
+pub(crate) fn regions(unit: &TranslationUnit) -> Vec<&Verbatim> {
+    let mut regions = vec![];
+    for item in &unit.items {
+        match item {
+            Item::Verbatim(verbatim) => regions.push(verbatim),
+            Item::Declaration(declaration) => declaration_regions(declaration, &mut regions),
+            Item::Function(function) => block_regions(&function.body, &mut regions),
+            _ => {}
+        }
+    }
+    regions
+}
+
+fn block_regions<'u>(block: &'u Block, regions: &mut Vec<&'u Verbatim>) {
+    for stmt in &block.stmts {
+        stmt_regions(stmt, regions);
+    }
+}
+
+fn stmt_regions<'u>(stmt: &'u Stmt, regions: &mut Vec<&'u Verbatim>) {
+    match stmt {
+        Stmt::Verbatim(verbatim) => regions.push(verbatim),
+        Stmt::Declaration(declaration) => declaration_regions(declaration, regions),
+        Stmt::Block(block) => block_regions(block, regions),
+        Stmt::Expr { expr, .. }
+        | Stmt::Jump {
+            value: Some(expr), ..
+        } => expr_regions(expr, regions),
+        Stmt::If {
+            cond,
+            then,
+            otherwise,
+            ..
+        } => {
+            expr_regions(&cond.expr, regions);
+            stmt_regions(then, regions);
+            if let Some((_, otherwise)) = otherwise {
+                stmt_regions(otherwise, regions);
+            }
+        }
+        Stmt::Switch { cond, body, .. } | Stmt::While { cond, body, .. } => {
+            expr_regions(&cond.expr, regions);
+            stmt_regions(body, regions);
+        }
+        Stmt::DoWhile { body, cond, .. } => {
+            stmt_regions(body, regions);
+            expr_regions(&cond.expr, regions);
+        }
+        Stmt::For {
+            init,
+            cond,
+            step,
+            body,
+            ..
+        } => {
+            match init {
+                ForInit::Declaration(declaration) => declaration_regions(declaration, regions),
+                ForInit::Expr(expr, _) => exprs_regions(expr, regions),
+            }
+            exprs_regions([cond, step].into_iter().flatten(), regions);
+            stmt_regions(body, regions);
+        }
+        Stmt::ForEach { body, .. } => stmt_regions(body, regions),
+        _ => {}
+    }
+}
+
+fn declaration_regions<'u>(declaration: &'u Declaration, regions: &mut Vec<&'u Verbatim>) {
+    let inits = declaration
+        .declarators
+        .iter()
+        .filter_map(|declarator| declarator.init.as_ref());
+    exprs_regions(inits.map(|(_, init)| init), regions);
+}
+
+/// the regions in the statement expressions inside `expr`, which are the only way for
+/// statements to be part of an expression
+fn expr_regions<'u>(expr: &'u Expr, regions: &mut Vec<&'u Verbatim>) {
+    match expr {
+        Expr::StmtExpr { block, .. } => block_regions(block, regions),
+        Expr::Paren { inner: operand, .. }
+        | Expr::Member { base: operand, .. }
+        | Expr::Postfix { operand, .. }
+        | Expr::Unary { operand, .. }
+        | Expr::Cast { operand, .. } => expr_regions(operand, regions),
+        Expr::Call { callee, args } => {
+            expr_regions(callee, regions);
+            exprs_regions(&args.args, regions);
+        }
+        Expr::Index {
+            base: lhs,
+            index: rhs,
+            ..
+        }
+        | Expr::Binary { lhs, rhs, .. } => exprs_regions([lhs, rhs].map(|expr| &**expr), regions),
+        Expr::Ternary {
+            cond,
+            then,
+            otherwise,
+            ..
+        } => {
+            expr_regions(cond, regions);
+            exprs_regions(then.as_deref(), regions);
+            expr_regions(otherwise, regions);
+        }
+        Expr::Generic {
+            controlling,
+            associations,
+            ..
+        } => {
+            expr_regions(controlling, regions);
+            exprs_regions(
+                associations.iter().map(|association| &association.value),
+                regions,
+            );
+        }
+        Expr::CompoundLiteral { init, .. } | Expr::InitList(init) => init_regions(init, regions),
+        _ => {}
+    }
+}
+
+fn exprs_regions<'u>(exprs: impl IntoIterator<Item = &'u Expr>, regions: &mut Vec<&'u Verbatim>) {
+    for expr in exprs {
+        expr_regions(expr, regions);
+    }
+}
+
+fn init_regions<'u>(init: &'u InitList, regions: &mut Vec<&'u Verbatim>) {
+    for entry in &init.entries {
+        if let InitEntry::Value { value, .. } = entry {
+            expr_regions(value, regions);
+        }
+    }
+}
+
+/// a warning for each region that is left unformatted
+pub(crate) fn warnings(tokens: &[Token], unit: &TranslationUnit) -> Vec<Warning> {
+    regions(unit)
+        .into_iter()
+        .map(|verbatim| {
+            let start = tokens[verbatim.start].location;
+            let end = tokens[verbatim.end - 1].location;
+            let error = tokens
+                .get(verbatim.error.at())
+                .map_or(end, |token| token.location);
+            Warning {
+                location: start,
+                message: format!(
+                    "lines {} to {} are left unformatted: {} at {}:{}",
+                    start.line + 1,
+                    end.line + tokens[verbatim.end - 1].token_type.height(),
+                    verbatim.error,
+                    error.line + 1,
+                    error.col + 1,
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{format_with_config, Config, Location};
+
+    #[test]
+    fn unparsable_statement_is_kept() {
+        let program = "int main(void)\n{\n    int a = 1;\n    a = @b\t+ 1;\n    return a;\n}\n";
+        let formatted = format_with_config(program, &Config::default());
+
+        assert_eq!(formatted.output, program);
+        assert_eq!(formatted.warnings.len(), 1);
+        assert_eq!(formatted.warnings[0].location, Location::new(3, 4));
+        assert_eq!(
+            formatted.warnings[0].message,
+            "lines 4 to 4 are left unformatted: expected an expression at 4:9"
+        );
+    }
+
+    #[test]
+    fn unparsable_declaration_is_kept() {
+        let program = "int x;\nstruct { int a; } $\t\t junk;\nint y;\n";
+        let formatted = format_with_config(program, &Config::default());
+
+        assert_eq!(formatted.output, program);
+        assert_eq!(formatted.warnings.len(), 1);
+        assert_eq!(formatted.warnings[0].location, Location::new(1, 0));
+    }
+
+    #[test]
+    fn stray_closer_is_kept_with_its_line() {
+        let program = "int a;\nint c = ] 3;\nstruct { int x; } ] broken;\nint d;\n";
+        let formatted = format_with_config(program, &Config::default());
+
+        assert_eq!(formatted.output, program);
+        assert_eq!(formatted.warnings.len(), 2);
+        assert_eq!(formatted.warnings[0].location, Location::new(1, 0));
+        assert_eq!(formatted.warnings[1].location, Location::new(2, 0));
+    }
+}
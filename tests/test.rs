@@ -22,7 +22,7 @@ mod tests {
             }
         }
         
-        if failed_cases.len() != 0
+        if !failed_cases.is_empty()
         {
             println!("Failed Examples:\n");
             for e in &failed_cases
@@ -33,6 +33,6 @@ mod tests {
             let nu = n.try_into().unwrap();
             return Err(nu);
         }
-        return Ok(());
+        Ok(())
     }
 }